@@ -1,104 +1,244 @@
-use std::{collections::BTreeMap, rc::Rc};
+use std::collections::BTreeMap;
 
 use itertools::Itertools;
-use strum::{IntoEnumIterator, VariantArray};
+use slotmap::SecondaryMap;
 
 use crate::{
-  week_calendar, ClassCalendar, ClassKey, Classroom, ClassroomAssignmentKey, ClassroomType,
+  week_calendar::{self, WeekLayout},
+  Availability, ClassCalendar, ClassKey, ClassroomAssignmentKey, ClassroomKey, ClassroomTypeKey,
   OptimizationConstraints,
 };
 
+/// Assigns a classroom to every class entry that can get one.
+///
+/// Each timeslot is matched independently, but whenever it doesn't cost a classroom
+/// assignment, a class is kept in the classroom of its previous contiguous session, or
+/// else in its home classroom (see [`home_classrooms`]). Neither depends on other days, so
+/// moving a class only changes the assignments of the days it touches.
 pub(crate) fn assign_classrooms(
   state: &ClassCalendar,
   constraints: &OptimizationConstraints,
-) -> BTreeMap<ClassroomAssignmentKey, Classroom> {
-  let matching = assign_classrooms_matching(state, constraints);
-  matching.collect()
+) -> BTreeMap<ClassroomAssignmentKey, ClassroomKey> {
+  let available_classrooms = available_classrooms(constraints);
+  let home_classrooms = home_classrooms(constraints, &available_classrooms);
+  state
+    .week_layout()
+    .days()
+    .flat_map(|day| {
+      assign_day_classrooms(
+        state,
+        constraints,
+        &available_classrooms,
+        &home_classrooms,
+        day,
+      )
+    })
+    .collect()
 }
 
+/// Classroom each class is kept in across the week when it's free. Classes are spread over the
+/// classrooms they can use, so classes of the same type don't all prefer the same one.
+fn home_classrooms(
+  constraints: &OptimizationConstraints,
+  available_classrooms: &SecondaryMap<ClassroomTypeKey, Vec<ClassroomKey>>,
+) -> SecondaryMap<ClassKey, ClassroomKey> {
+  let mut homes_per_classroom: SecondaryMap<ClassroomKey, usize> = SecondaryMap::new();
+  let mut home_classrooms = SecondaryMap::new();
+  for (class_key, class) in constraints.classes.iter() {
+    let home_classroom = class
+      .allowed_classroom_types
+      .iter()
+      .filter_map(|classroom_type_key| available_classrooms.get(*classroom_type_key))
+      .flatten()
+      .filter(|classroom_key| {
+        constraints.classrooms[**classroom_key]
+          .capacity
+          .is_none_or(|capacity| capacity >= class.enrollment)
+      })
+      .min_by_key(|classroom_key| {
+        (
+          homes_per_classroom
+            .get(**classroom_key)
+            .copied()
+            .unwrap_or(0),
+          **classroom_key,
+        )
+      });
+    if let Some(home_classroom) = home_classroom {
+      *homes_per_classroom
+        .entry(*home_classroom)
+        .unwrap()
+        .or_default() += 1;
+      home_classrooms.insert(class_key, *home_classroom);
+    }
+  }
+  home_classrooms
+}
+
+fn assign_day_classrooms(
+  state: &ClassCalendar,
+  constraints: &OptimizationConstraints,
+  available_classrooms: &SecondaryMap<ClassroomTypeKey, Vec<ClassroomKey>>,
+  home_classrooms: &SecondaryMap<ClassKey, ClassroomKey>,
+  day: week_calendar::Day,
+) -> Vec<(ClassroomAssignmentKey, ClassroomKey)> {
+  let mut assignments = Vec::new();
+  let mut last_assignment: SecondaryMap<ClassKey, (ClassroomAssignmentKey, ClassroomKey)> =
+    SecondaryMap::new();
+  for timeslot in state.week_layout().timeslots() {
+    let mut edges = timeslot_edges(state, constraints, day, timeslot, available_classrooms);
+    let matching_size = hopcroft_karp::matching_size(&edges);
+    let class_vertices = edges
+      .iter()
+      .filter_map(|(a, _b)| match a {
+        ClassroomAssignmentVertex::Class(class_key, index) => Some((*class_key, *index)),
+        _ => None,
+      })
+      .unique()
+      .collect_vec();
+    for (class_key, index) in class_vertices {
+      let contiguous_classroom = last_assignment
+        .get(class_key)
+        .filter(|(k, _classroom_key)| usize::from(k.timeslot) + 1 == usize::from(timeslot))
+        .map(|(_k, classroom_key)| *classroom_key);
+      let preferred_classrooms = contiguous_classroom
+        .into_iter()
+        .chain(home_classrooms.get(class_key).copied());
+      for classroom_key in preferred_classrooms {
+        let class_vertex = ClassroomAssignmentVertex::Class(class_key, index);
+        let classroom_vertex = ClassroomAssignmentVertex::Classroom(classroom_key);
+        if !edges.contains(&(class_vertex, classroom_vertex)) {
+          continue;
+        }
+        let fixed_edges = edges
+          .iter()
+          .filter(|(a, b)| (*a == class_vertex) == (*b == classroom_vertex))
+          .copied()
+          .collect_vec();
+        if hopcroft_karp::matching_size(&fixed_edges) == matching_size {
+          edges = fixed_edges;
+          break;
+        }
+      }
+    }
+    for (k, classroom_key) in matching_assignments(edges, day, timeslot) {
+      last_assignment.insert(k.class_key, (k, classroom_key));
+      assignments.push((k, classroom_key));
+    }
+  }
+  assignments
+}
+
+/// A class hour in a timeslot, or a classroom. Classes with more than one hour in the timeslot
+/// get a vertex per hour.
 #[derive(Hash, Clone, Copy, PartialEq, Eq)]
 enum ClassroomAssignmentVertex {
-  Class(ClassKey),
-  Classroom(Classroom),
+  Class(ClassKey, u8),
+  Classroom(ClassroomKey),
 }
 
-fn timeslot_assign_classrooms<'a>(
-  state: &'a ClassCalendar,
-  constraints: &'a OptimizationConstraints,
+fn available_classrooms(
+  constraints: &OptimizationConstraints,
+) -> SecondaryMap<ClassroomTypeKey, Vec<ClassroomKey>> {
+  let mut available_classrooms: SecondaryMap<ClassroomTypeKey, Vec<ClassroomKey>> =
+    SecondaryMap::from_iter(constraints.classroom_types.keys().map(|k| (k, Vec::new())));
+  for (classroom_key, classroom) in constraints.classrooms.iter() {
+    if let Some(classrooms) = available_classrooms.get_mut(classroom.classroom_type) {
+      classrooms.push(classroom_key);
+    }
+  }
+  available_classrooms
+}
+
+fn timeslot_edges(
+  state: &ClassCalendar,
+  constraints: &OptimizationConstraints,
   day: week_calendar::Day,
   timeslot: week_calendar::Timeslot,
-  available_classrooms: Rc<[Vec<Classroom>; ClassroomType::VARIANTS.len()]>,
-) -> impl Iterator<Item = (ClassroomAssignmentKey, Classroom)> + 'a {
+  available_classrooms: &SecondaryMap<ClassroomTypeKey, Vec<ClassroomKey>>,
+) -> Vec<(ClassroomAssignmentVertex, ClassroomAssignmentVertex)> {
   let mut edges: Vec<(ClassroomAssignmentVertex, ClassroomAssignmentVertex)> = Vec::new();
-  for class_key in state
-    .iter_class_keys()
-    .filter(|k| state.get_count(day, timeslot, *k) > 0)
-  {
+  for class_key in state.iter_class_keys() {
+    let count = state.get_count(day, timeslot, class_key);
+    if count == 0 {
+      continue;
+    }
     let class = constraints.classes.get(class_key).unwrap();
     let classrooms = class
       .allowed_classroom_types
       .iter()
-      .map(|classroom_type| {
-        ClassroomType::VARIANTS
-          .iter()
-          .position(|v| *v == classroom_type)
-          .unwrap()
+      .filter_map(|classroom_type_key| available_classrooms.get(*classroom_type_key))
+      .flatten()
+      .filter(|classroom_key| {
+        let classroom = &constraints.classrooms[**classroom_key];
+        !matches!(
+          classroom.availability.get(day, timeslot),
+          Availability::NotAvailable
+        ) && classroom
+          .capacity
+          .is_none_or(|capacity| capacity >= class.enrollment)
       })
-      .flat_map(|classroom_type_i| &available_classrooms[classroom_type_i])
       .unique();
-    for classroom in classrooms {
-      edges.push((
-        ClassroomAssignmentVertex::Class(class_key),
-        ClassroomAssignmentVertex::Classroom(*classroom),
-      ));
+    for classroom_key in classrooms {
+      for index in 0..count {
+        edges.push((
+          ClassroomAssignmentVertex::Class(class_key, index),
+          ClassroomAssignmentVertex::Classroom(*classroom_key),
+        ));
+      }
     }
   }
-  let matching = hopcroft_karp::matching(&edges);
-  matching.into_iter().map(move |(a, b)| match (a, b) {
-    (
-      ClassroomAssignmentVertex::Class(class_key),
-      ClassroomAssignmentVertex::Classroom(classroom),
-    ) => (
-      ClassroomAssignmentKey {
-        day,
-        timeslot,
-        class_key,
-      },
-      classroom,
-    ),
-    _ => unreachable!(),
+  edges
+}
+
+fn matching_assignments(
+  edges: Vec<(ClassroomAssignmentVertex, ClassroomAssignmentVertex)>,
+  day: week_calendar::Day,
+  timeslot: week_calendar::Timeslot,
+) -> impl Iterator<Item = (ClassroomAssignmentKey, ClassroomKey)> {
+  let matching = hopcroft_karp::matching(&edges)
+    .into_iter()
+    .map(|(a, b)| match (a, b) {
+      (
+        ClassroomAssignmentVertex::Class(class_key, index),
+        ClassroomAssignmentVertex::Classroom(classroom_key),
+      ) => (class_key, index, classroom_key),
+      _ => unreachable!(),
+    })
+    .sorted_by_key(|(class_key, index, _classroom_key)| (*class_key, *index));
+  // The hours of a class in a timeslot are interchangeable, so the ones that got a classroom
+  // take the lowest indices
+  let mut matched_hours: SecondaryMap<ClassKey, u8> = SecondaryMap::new();
+  matching.map(move |(class_key, _index, classroom_key)| {
+    let index = matched_hours.entry(class_key).unwrap().or_default();
+    let k = ClassroomAssignmentKey {
+      day,
+      timeslot,
+      class_key,
+      index: *index,
+    };
+    *index += 1;
+    (k, classroom_key)
   })
 }
 
-fn iter_week() -> impl Iterator<Item = (week_calendar::Day, week_calendar::Timeslot)> {
-  week_calendar::Day::all().flat_map(|d| week_calendar::Timeslot::all().map(move |t| (d, t)))
+fn iter_week(
+  week_layout: &WeekLayout,
+) -> impl Iterator<Item = (week_calendar::Day, week_calendar::Timeslot)> + '_ {
+  week_layout
+    .days()
+    .flat_map(|d| week_layout.timeslots().map(move |t| (d, t)))
 }
 
 fn assign_classrooms_matching<'a>(
   state: &'a ClassCalendar,
   constraints: &'a OptimizationConstraints,
-) -> impl Iterator<Item = (ClassroomAssignmentKey, Classroom)> + 'a {
-  let available_classrooms: [Vec<Classroom>; ClassroomType::VARIANTS.len()] =
-    ClassroomType::VARIANTS
-      .iter()
-      .map(|v| {
-        Classroom::iter()
-          .filter(|c| c.get_type() == *v)
-          .collect_vec()
-      })
-      .collect_vec()
-      .try_into()
-      .unwrap();
-  let available_classrooms = Rc::new(available_classrooms);
+) -> impl Iterator<Item = (ClassroomAssignmentKey, ClassroomKey)> + 'a {
+  let available_classrooms = available_classrooms(constraints);
 
-  iter_week().flat_map(move |(day, timeslot)| {
-    timeslot_assign_classrooms(
-      state,
-      constraints,
-      day,
-      timeslot,
-      available_classrooms.clone(),
-    )
+  iter_week(state.week_layout()).flat_map(move |(day, timeslot)| {
+    let edges = timeslot_edges(state, constraints, day, timeslot, &available_classrooms);
+    matching_assignments(edges, day, timeslot)
   })
 }
 
@@ -112,3 +252,269 @@ pub(crate) fn count_classroom_assignment_collisions(
     .checked_sub(assign_classrooms_matching(state, constraints).count())
     .expect("Can't be more matching than class entries")
 }
+
+/// Number of class hours in the timeslot that can't get a classroom.
+pub(crate) fn count_timeslot_classroom_assignment_collisions(
+  state: &ClassCalendar,
+  constraints: &OptimizationConstraints,
+  day: week_calendar::Day,
+  timeslot: week_calendar::Timeslot,
+) -> u64 {
+  let class_hours: u64 = state
+    .iter_class_keys()
+    .map(|class_key| state.get_count(day, timeslot, class_key) as u64)
+    .sum();
+  let edges = timeslot_edges(
+    state,
+    constraints,
+    day,
+    timeslot,
+    &available_classrooms(constraints),
+  );
+  class_hours
+    .checked_sub(hopcroft_karp::matching_size(&edges) as u64)
+    .expect("Can't be more matching than class hours")
+}
+
+/// Counts how many times a class changes classroom between two consecutive sessions of the
+/// same day.
+pub(crate) fn count_classroom_changes(
+  state: &ClassCalendar,
+  constraints: &OptimizationConstraints,
+) -> u64 {
+  classroom_changes(state, constraints).len() as u64
+}
+
+/// [`count_classroom_changes`] of a single day.
+pub(crate) fn count_day_classroom_changes(
+  state: &ClassCalendar,
+  constraints: &OptimizationConstraints,
+  day: week_calendar::Day,
+) -> u64 {
+  let available_classrooms = available_classrooms(constraints);
+  let home_classrooms = home_classrooms(constraints, &available_classrooms);
+  day_classroom_changes(assign_day_classrooms(
+    state,
+    constraints,
+    &available_classrooms,
+    &home_classrooms,
+    day,
+  ))
+  .len() as u64
+}
+
+/// Pairs of consecutive sessions of a class in the same day that are assigned different
+/// classrooms. Changes between days are left to the home classrooms.
+pub(crate) fn classroom_changes(
+  state: &ClassCalendar,
+  constraints: &OptimizationConstraints,
+) -> Vec<(ClassroomAssignmentKey, ClassroomAssignmentKey)> {
+  assign_classrooms(state, constraints)
+    .into_iter()
+    .group_by(|(k, _classroom_key)| k.day)
+    .into_iter()
+    .flat_map(|(_day, assignments)| day_classroom_changes(assignments))
+    .collect()
+}
+
+fn day_classroom_changes(
+  assignments: impl IntoIterator<Item = (ClassroomAssignmentKey, ClassroomKey)>,
+) -> Vec<(ClassroomAssignmentKey, ClassroomAssignmentKey)> {
+  let mut last_assignment: SecondaryMap<ClassKey, (ClassroomAssignmentKey, ClassroomKey)> =
+    SecondaryMap::new();
+  let mut classroom_changes = Vec::new();
+  for (k, classroom_key) in assignments
+    .into_iter()
+    .sorted_by_key(|(k, _classroom_key)| *k)
+  {
+    if let Some((last_k, last_classroom_key)) =
+      last_assignment.insert(k.class_key, (k, classroom_key))
+    {
+      // Hours of a class in the same timeslot can't share a classroom
+      if last_k.timeslot != k.timeslot && last_classroom_key != classroom_key {
+        classroom_changes.push((last_k, k));
+      }
+    }
+  }
+  classroom_changes
+}
+
+#[cfg(test)]
+mod test {
+  use crate::{
+    SchoolSchedule, DAY_MONDAY, DAY_TUESDAY, TIMESLOT_08_00, TIMESLOT_09_00, TIMESLOT_10_00,
+  };
+
+  use super::*;
+
+  #[test]
+  fn test_assign_classrooms() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type = schedule.add_new_classroom_type();
+    let lab_classroom_type = schedule.add_new_classroom_type();
+    let classroom = schedule.add_new_classroom(classroom_type);
+    let _lab = schedule.add_new_classroom(lab_classroom_type);
+    *schedule
+      .get_classroom_mut(classroom)
+      .unwrap()
+      .availability
+      .get_mut(DAY_TUESDAY, TIMESLOT_08_00) = Availability::NotAvailable;
+    let p0 = schedule.add_new_professor();
+    let k0 = schedule.add_new_class(p0);
+    let k1 = schedule.add_new_class(p0);
+    for class_key in [k0, k1] {
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_allowed_classroom_types([classroom_type].into());
+      class_entry.set_hours(1);
+    }
+    let constraints = schedule.get_simulation_constraints();
+
+    let mut state = schedule.class_calendar().clone();
+    let assignments = assign_classrooms(&state, constraints);
+    assert_eq!(assignments.len(), 1);
+    assert_eq!(assignments.values().next(), Some(&classroom));
+    assert_eq!(
+      count_classroom_assignment_collisions(&state, constraints),
+      1
+    );
+
+    state.move_one_class(DAY_MONDAY, TIMESLOT_08_00, DAY_TUESDAY, TIMESLOT_08_00, k1);
+    assert_eq!(
+      count_classroom_assignment_collisions(&state, constraints),
+      1
+    );
+    assert!(
+      !assign_classrooms(&state, constraints).contains_key(&ClassroomAssignmentKey {
+        day: DAY_TUESDAY,
+        timeslot: TIMESLOT_08_00,
+        class_key: k1,
+        index: 0,
+      })
+    );
+  }
+
+  #[test]
+  fn test_assign_classrooms_repeated_class() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type = schedule.add_new_classroom_type();
+    let c0 = schedule.add_new_classroom(classroom_type);
+    let c1 = schedule.add_new_classroom(classroom_type);
+    let p0 = schedule.add_new_professor();
+    let k0 = schedule.add_new_class(p0);
+    let mut class_entry = schedule.get_class_entry(k0).unwrap();
+    class_entry.set_allowed_classroom_types([classroom_type].into());
+    class_entry.set_hours(3);
+    let constraints = schedule.get_simulation_constraints();
+
+    // Two of the three hours in the timeslot get a classroom each
+    let state = schedule.class_calendar().clone();
+    let assignments = assign_classrooms(&state, constraints);
+    assert_eq!(
+      assignments.keys().map(|k| k.index).sorted().collect_vec(),
+      vec![0, 1]
+    );
+    assert_eq!(
+      assignments.values().copied().sorted().collect_vec(),
+      [c0, c1].into_iter().sorted().collect_vec()
+    );
+    assert_eq!(
+      count_classroom_assignment_collisions(&state, constraints),
+      1
+    );
+    assert_eq!(count_classroom_changes(&state, constraints), 0);
+    schedule
+      .replace_class_calendar(schedule.class_calendar().clone())
+      .unwrap();
+    assert_eq!(
+      schedule
+        .classroom_assignment_keys()
+        .filter_map(|k| schedule.get_classroom_assignment(&k))
+        .count(),
+      2
+    );
+  }
+
+  #[test]
+  fn test_assign_classrooms_capacity() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type = schedule.add_new_classroom_type();
+    let small_classroom = schedule.add_new_classroom(classroom_type);
+    let big_classroom = schedule.add_new_classroom(classroom_type);
+    schedule
+      .get_classroom_mut(small_classroom)
+      .unwrap()
+      .capacity = Some(20);
+    schedule.get_classroom_mut(big_classroom).unwrap().capacity = Some(40);
+    let p0 = schedule.add_new_professor();
+    let k0 = schedule.add_new_class(p0);
+    let k1 = schedule.add_new_class(p0);
+    for class_key in [k0, k1] {
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_allowed_classroom_types([classroom_type].into());
+      class_entry.set_enrollment(30);
+      class_entry.set_hours(1);
+    }
+    let constraints = schedule.get_simulation_constraints();
+
+    let mut state = schedule.class_calendar().clone();
+    assert_eq!(
+      count_classroom_assignment_collisions(&state, constraints),
+      1
+    );
+
+    state.move_one_class(DAY_MONDAY, TIMESLOT_08_00, DAY_TUESDAY, TIMESLOT_08_00, k1);
+    assert_eq!(
+      count_classroom_assignment_collisions(&state, constraints),
+      0
+    );
+    let assignments = assign_classrooms(&state, constraints);
+    assert!(assignments.values().all(|k| *k == big_classroom));
+
+    schedule.get_class_entry(k0).unwrap().set_enrollment(50);
+    let constraints = schedule.get_simulation_constraints();
+    assert_eq!(
+      count_classroom_assignment_collisions(&state, constraints),
+      1
+    );
+  }
+
+  #[test]
+  fn test_assign_classrooms_stable() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type = schedule.add_new_classroom_type();
+    for _ in 0..3 {
+      schedule.add_new_classroom(classroom_type);
+    }
+    let p0 = schedule.add_new_professor();
+    let k0 = schedule.add_new_class(p0);
+    let k1 = schedule.add_new_class(p0);
+    let k2 = schedule.add_new_class(p0);
+    for (class_key, hours) in [(k0, 4), (k1, 2), (k2, 1)] {
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_allowed_classroom_types([classroom_type].into());
+      class_entry.set_hours(hours);
+    }
+    let mut state = schedule.class_calendar().clone();
+    for (day, timeslot) in [
+      (DAY_MONDAY, TIMESLOT_09_00),
+      (DAY_MONDAY, TIMESLOT_10_00),
+      (DAY_TUESDAY, TIMESLOT_08_00),
+    ] {
+      state.move_one_class(DAY_MONDAY, TIMESLOT_08_00, day, timeslot, k0);
+    }
+    state.move_one_class(DAY_MONDAY, TIMESLOT_08_00, DAY_MONDAY, TIMESLOT_09_00, k1);
+    state.move_one_class(DAY_MONDAY, TIMESLOT_08_00, DAY_TUESDAY, TIMESLOT_08_00, k2);
+    let constraints = schedule.get_simulation_constraints();
+
+    let assignments = assign_classrooms(&state, constraints);
+    assert_eq!(assignments.len(), state.class_entries().len());
+    let k0_classrooms = assignments
+      .iter()
+      .filter(|(k, _classroom_key)| k.class_key == k0)
+      .map(|(_k, classroom_key)| *classroom_key)
+      .collect_vec();
+    assert_eq!(k0_classrooms.len(), 4);
+    assert!(k0_classrooms.iter().all_equal());
+    assert_eq!(count_classroom_changes(&state, constraints), 0);
+  }
+}
@@ -3,7 +3,6 @@ mod optimization;
 mod school_schedule;
 mod week_calendar;
 
-pub use enumflags2;
 pub use slotmap;
 pub use strum;
 
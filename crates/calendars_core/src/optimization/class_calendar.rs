@@ -1,6 +1,8 @@
 use crate::week_calendar;
 use crate::week_calendar::WeekCalendar;
+use crate::week_calendar::WeekLayout;
 use crate::ClassKey;
+use rand::seq::IteratorRandom;
 use serde::Deserialize;
 use serde::Serialize;
 use slotmap::SecondaryMap;
@@ -10,13 +12,36 @@ pub struct SingleClassEntry {
   pub day: week_calendar::Day,
   pub timeslot: week_calendar::Timeslot,
   pub class_key: ClassKey,
+  /// Locked entries are never moved by the optimizer.
+  #[serde(default)]
+  pub locked: bool,
+}
+
+/// Contiguous hours of a class in a day.
+struct Session {
+  class_key: ClassKey,
+  day: week_calendar::Day,
+  /// Index of the first timeslot.
+  start: usize,
+  len: usize,
+}
+
+impl SingleClassEntry {
+  fn is_at(
+    &self,
+    day: week_calendar::Day,
+    timeslot: week_calendar::Timeslot,
+    class_key: ClassKey,
+  ) -> bool {
+    self.day == day && self.timeslot == timeslot && self.class_key == class_key
+  }
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum MoveOneClassRandomError {
   #[error("Tried to move a class randomly, but the destination was full")]
   RandomChosenDestinationFull,
-  #[error("The calendar is empty (the total class count is 0)")]
+  #[error("The calendar has no unlocked classes (the total class count is 0 or all are locked)")]
   NoClassesToMove,
 }
 
@@ -49,11 +74,106 @@ pub struct ClassEntryDelta {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ClassCalendar {
+  #[serde(default)]
+  week_layout: WeekLayout,
   data: SecondaryMap<ClassKey, WeekCalendar<u8>>,
   class_entries: Vec<SingleClassEntry>,
 }
 
 impl ClassCalendar {
+  pub fn new(week_layout: WeekLayout) -> Self {
+    Self {
+      week_layout,
+      data: Default::default(),
+      class_entries: Default::default(),
+    }
+  }
+
+  pub fn week_layout(&self) -> &WeekLayout {
+    &self.week_layout
+  }
+
+  /// Whether every class hour and calendar fits in the week layout, as calendars read from a
+  /// file may not.
+  pub(crate) fn fits_week_layout(&self) -> bool {
+    self
+      .data
+      .values()
+      .all(|calendar| calendar.fits(&self.week_layout))
+      && self
+        .class_entries
+        .iter()
+        .all(|entry| self.week_layout.contains(entry.day, entry.timeslot))
+  }
+
+  /// Copies the calendar into a new layout, keeping the class hours. Classes are moved to the
+  /// timeslot with the same start time, or to the nearest one if the layout doesn't have it.
+  pub(crate) fn with_week_layout(&self, week_layout: WeekLayout) -> ClassCalendar {
+    let mut class_calendar = ClassCalendar::new(week_layout.clone());
+    for class_key in self.iter_class_keys() {
+      class_calendar
+        .data
+        .insert(class_key, WeekCalendar::new(&week_layout));
+    }
+    let last_day = week_layout.day_count() - 1;
+    for entry in self.class_entries.iter() {
+      let day = week_calendar::Day::from_usize(usize::from(entry.day).min(last_day)).unwrap();
+      let start = self.week_layout.timeslot_start(entry.timeslot);
+      let timeslot = week_layout
+        .timeslots()
+        .min_by_key(|t| (week_layout.timeslot_start(*t) - start).num_minutes().abs())
+        .unwrap();
+      class_calendar
+        .add_one_class(day, timeslot, entry.class_key)
+        .expect("A class can't have more than 255 hours");
+      class_calendar.class_entries.last_mut().unwrap().locked = entry.locked;
+    }
+    class_calendar
+  }
+
+  pub fn is_locked(
+    &self,
+    day: week_calendar::Day,
+    timeslot: week_calendar::Timeslot,
+    class_key: ClassKey,
+  ) -> bool {
+    self
+      .class_entries
+      .iter()
+      .any(|entry| entry.is_at(day, timeslot, class_key) && entry.locked)
+  }
+
+  /// Locks or unlocks every entry of the class in the given day and timeslot.
+  pub(crate) fn set_locked(
+    &mut self,
+    day: week_calendar::Day,
+    timeslot: week_calendar::Timeslot,
+    class_key: ClassKey,
+    locked: bool,
+  ) {
+    self
+      .class_entries
+      .iter_mut()
+      .filter(|entry| entry.is_at(day, timeslot, class_key))
+      .for_each(|entry| entry.locked = locked);
+  }
+
+  /// Index of an entry of the class in the given day and timeslot, preferring unlocked entries.
+  fn find_entry(
+    &self,
+    day: week_calendar::Day,
+    timeslot: week_calendar::Timeslot,
+    class_key: ClassKey,
+  ) -> Option<usize> {
+    self
+      .class_entries
+      .iter()
+      .enumerate()
+      .filter(|(_i, entry)| entry.is_at(day, timeslot, class_key))
+      .min_by_key(|(_i, entry)| entry.locked)
+      .map(|(i, _entry)| i)
+  }
+
   pub fn class_entries(&self) -> &Vec<SingleClassEntry> {
     &self.class_entries
   }
@@ -89,20 +209,23 @@ impl ClassCalendar {
       .unwrap_or(0)
   }
 
-  fn move_one_class_random_delta<R: rand::Rng>(
+  /// Picks a random move of an unlocked class without applying it.
+  pub(crate) fn move_one_class_random_delta<R: rand::Rng>(
     &self,
     rng: &mut R,
   ) -> Result<(ClassEntryDelta, usize), MoveOneClassRandomError> {
-    if self.class_entries.is_empty() {
-      return Err(MoveOneClassRandomError::NoClassesToMove);
-    }
-    let entry_index = rng.gen_range(0..self.class_entries.len());
-    let entry = self.class_entries.get(entry_index).unwrap();
+    let (entry_index, entry) = self
+      .class_entries
+      .iter()
+      .enumerate()
+      .filter(|(_i, entry)| !entry.locked)
+      .choose(rng)
+      .ok_or(MoveOneClassRandomError::NoClassesToMove)?;
     let class_key = entry.class_key;
     let src_day = entry.day;
     let src_timeslot = entry.timeslot;
-    let dst_day = week_calendar::Day::new_random(rng);
-    let dst_timeslot = week_calendar::Timeslot::new_random(rng);
+    let dst_day = self.week_layout.random_day(rng);
+    let dst_timeslot = self.week_layout.random_timeslot(rng);
     self
       .get_count(dst_day, dst_timeslot, class_key)
       .checked_add(1)
@@ -119,7 +242,116 @@ impl ClassCalendar {
       .ok_or(MoveOneClassRandomError::RandomChosenDestinationFull)
   }
 
-  /// Moves one random class to a random day and time.
+  fn has_unlocked_entry(
+    &self,
+    day: week_calendar::Day,
+    timeslot: week_calendar::Timeslot,
+    class_key: ClassKey,
+  ) -> bool {
+    self
+      .class_entries
+      .iter()
+      .any(|entry| entry.is_at(day, timeslot, class_key) && !entry.locked)
+  }
+
+  /// The unlocked hours of a random class that are contiguous in a day.
+  fn random_session<R: rand::Rng>(&self, rng: &mut R) -> Option<Session> {
+    let entry = self
+      .class_entries
+      .iter()
+      .filter(|entry| !entry.locked)
+      .choose(rng)?;
+    let is_part = |timeslot: usize| {
+      week_calendar::Timeslot::from_usize(timeslot)
+        .filter(|timeslot| self.week_layout.contains(entry.day, *timeslot))
+        .is_some_and(|timeslot| self.has_unlocked_entry(entry.day, timeslot, entry.class_key))
+    };
+    let mut start = usize::from(entry.timeslot);
+    while start > 0 && is_part(start - 1) {
+      start -= 1;
+    }
+    let mut end = usize::from(entry.timeslot) + 1;
+    while is_part(end) {
+      end += 1;
+    }
+    Some(Session {
+      class_key: entry.class_key,
+      day: entry.day,
+      start,
+      len: end - start,
+    })
+  }
+
+  /// Moves of every hour of the session so that it starts at the given day and timeslot, or
+  /// `None` if it doesn't fit in the day.
+  fn session_deltas(
+    &self,
+    session: &Session,
+    dst_day: week_calendar::Day,
+    dst_start: usize,
+  ) -> Option<Vec<ClassEntryDelta>> {
+    (0..session.len)
+      .map(|i| {
+        let src_timeslot = week_calendar::Timeslot::from_usize(session.start + i)?;
+        let dst_timeslot = week_calendar::Timeslot::from_usize(dst_start + i)
+          .filter(|timeslot| self.week_layout.contains(dst_day, *timeslot))?;
+        self
+          .get_count(dst_day, dst_timeslot, session.class_key)
+          .checked_add(1)?;
+        Some(ClassEntryDelta {
+          class_key: session.class_key,
+          src_day: session.day,
+          src_timeslot,
+          dst_day,
+          dst_timeslot,
+        })
+      })
+      .collect()
+  }
+
+  /// Picks a random session and moves all of it to a random day and time, without applying it.
+  pub(crate) fn move_session_random_deltas<R: rand::Rng>(
+    &self,
+    rng: &mut R,
+  ) -> Option<Vec<ClassEntryDelta>> {
+    let session = self.random_session(rng)?;
+    let dst_day = self.week_layout.random_day(rng);
+    let dst_start = self.week_layout.random_timeslot(rng);
+    self.session_deltas(&session, dst_day, usize::from(dst_start))
+  }
+
+  /// Picks a random session and moves it to the same timeslots of another day, without
+  /// applying it.
+  pub(crate) fn shift_session_day_random_deltas<R: rand::Rng>(
+    &self,
+    rng: &mut R,
+  ) -> Option<Vec<ClassEntryDelta>> {
+    let session = self.random_session(rng)?;
+    let dst_day = self
+      .week_layout
+      .days()
+      .filter(|day| *day != session.day)
+      .choose(rng)?;
+    self.session_deltas(&session, dst_day, session.start)
+  }
+
+  /// Picks two random sessions of different classes and exchanges their start, without
+  /// applying it.
+  pub(crate) fn swap_sessions_random_deltas<R: rand::Rng>(
+    &self,
+    rng: &mut R,
+  ) -> Option<Vec<ClassEntryDelta>> {
+    let a = self.random_session(rng)?;
+    let b = self.random_session(rng)?;
+    if a.class_key == b.class_key {
+      return None;
+    }
+    let mut deltas = self.session_deltas(&a, b.day, b.start)?;
+    deltas.extend(self.session_deltas(&b, a.day, a.start)?);
+    Some(deltas)
+  }
+
+  /// Moves one random unlocked class to a random day and time.
   pub(crate) fn move_one_class_random<R: rand::Rng>(
     &mut self,
     rng: &mut R,
@@ -158,7 +390,12 @@ impl ClassCalendar {
     timeslot: week_calendar::Timeslot,
     class_key: ClassKey,
   ) -> Result<u8, AddOneClassError> {
-    let calendar = self.data.entry(class_key).unwrap().or_default();
+    let week_layout = &self.week_layout;
+    let calendar = self
+      .data
+      .entry(class_key)
+      .unwrap()
+      .or_insert_with(|| WeekCalendar::new(week_layout));
     let r = calendar.get(day, timeslot).checked_add(1);
     match r {
       Some(new_count) => {
@@ -167,6 +404,7 @@ impl ClassCalendar {
           day,
           timeslot,
           class_key,
+          locked: false,
         });
         Ok(new_count)
       }
@@ -182,15 +420,7 @@ impl ClassCalendar {
     class_key: ClassKey,
   ) -> Result<u8, RemoveOneClassError> {
     let entry_idx = self
-      .class_entries
-      .iter()
-      .position(|x| {
-        *x == SingleClassEntry {
-          day,
-          timeslot,
-          class_key,
-        }
-      })
+      .find_entry(day, timeslot, class_key)
       .ok_or(RemoveOneClassError::SourceEmpty)?;
     self.class_entries.swap_remove(entry_idx);
     let new_count = self
@@ -210,7 +440,10 @@ impl ClassCalendar {
     let entry_idx = self
       .class_entries
       .iter()
-      .position(|x| x.class_key == class_key)
+      .enumerate()
+      .filter(|(_i, entry)| entry.class_key == class_key)
+      .min_by_key(|(_i, entry)| entry.locked)
+      .map(|(i, _entry)| i)
       .ok_or(RemoveOneClassAnywhereError::NoClasses)?;
     let entry = self.class_entries.swap_remove(entry_idx);
     let new_count = self
@@ -227,9 +460,14 @@ impl ClassCalendar {
 
 #[cfg(test)]
 mod test {
-  use rand::rngs::ThreadRng;
+  use itertools::Itertools;
+  use rand::{rngs::ThreadRng, SeedableRng};
+  use rand_chacha::ChaCha8Rng;
 
-  use crate::{Day, OptimizationConstraints, Timeslot, DAY_MONDAY, TIMESLOT_08_00};
+  use crate::{
+    Day, OptimizationConstraints, Timeslot, DAY_FRIDAY, DAY_MONDAY, DAY_THURSDAY, DAY_WEDNESDAY,
+    TIMESLOT_08_00, TIMESLOT_09_00, TIMESLOT_10_00, TIMESLOT_12_00, TIMESLOT_15_00, TIMESLOT_19_00,
+  };
 
   use super::*;
 
@@ -284,8 +522,8 @@ mod test {
       .unwrap();
     assert_eq!(calendar.get_count(DAY_MONDAY, TIMESLOT_08_00, k1), 1);
     let delta = calendar.move_one_class_random(&mut rng).unwrap();
-    for day in Day::all() {
-      for timeslot in Timeslot::all() {
+    for day in calendar.week_layout().days() {
+      for timeslot in calendar.week_layout().timeslots() {
         if day == delta.dst_day && timeslot == delta.dst_timeslot {
           assert_eq!(calendar.get_count(day, timeslot, k1), 1);
         } else {
@@ -294,4 +532,119 @@ mod test {
       }
     }
   }
+
+  #[test]
+  fn test_session_moves() {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let mut constraints = OptimizationConstraints::default();
+    let k1 = constraints.classes.insert(Default::default());
+    let k2 = constraints.classes.insert(Default::default());
+    let mut calendar = ClassCalendar::default();
+    for timeslot in [TIMESLOT_08_00, TIMESLOT_09_00, TIMESLOT_10_00] {
+      calendar.add_one_class(DAY_MONDAY, timeslot, k1).unwrap();
+    }
+    calendar
+      .add_one_class(DAY_WEDNESDAY, TIMESLOT_15_00, k1)
+      .unwrap();
+    calendar
+      .add_one_class(DAY_FRIDAY, TIMESLOT_12_00, k2)
+      .unwrap();
+    calendar.set_locked(DAY_FRIDAY, TIMESLOT_12_00, k2, true);
+
+    for _ in 0..100 {
+      let deltas = calendar.shift_session_day_random_deltas(&mut rng).unwrap();
+      assert!([1, 3].contains(&deltas.len()));
+      for delta in deltas.iter() {
+        assert_eq!(delta.class_key, k1);
+        assert_ne!(delta.dst_day, delta.src_day);
+        assert_eq!(delta.dst_timeslot, delta.src_timeslot);
+      }
+
+      if let Some(deltas) = calendar.move_session_random_deltas(&mut rng) {
+        assert!([1, 3].contains(&deltas.len()));
+        for (a, b) in deltas.iter().tuple_windows() {
+          assert_eq!(a.dst_day, b.dst_day);
+          assert_eq!(usize::from(a.dst_timeslot) + 1, usize::from(b.dst_timeslot));
+        }
+      }
+
+      // Only one class has unlocked sessions.
+      assert!(calendar.swap_sessions_random_deltas(&mut rng).is_none());
+    }
+
+    calendar.set_locked(DAY_FRIDAY, TIMESLOT_12_00, k2, false);
+    let deltas = (0..100)
+      .find_map(|_| calendar.swap_sessions_random_deltas(&mut rng))
+      .unwrap();
+    let k1_delta = deltas.iter().find(|delta| delta.class_key == k1).unwrap();
+    let k2_delta = deltas.iter().find(|delta| delta.class_key == k2).unwrap();
+    assert_eq!(
+      (k1_delta.dst_day, k1_delta.dst_timeslot),
+      (DAY_FRIDAY, TIMESLOT_12_00)
+    );
+    assert_eq!(
+      (k2_delta.dst_day, k2_delta.dst_timeslot),
+      (k1_delta.src_day, k1_delta.src_timeslot)
+    );
+  }
+
+  #[test]
+  fn test_with_week_layout() {
+    let mut constraints = OptimizationConstraints::default();
+    let k1 = constraints.classes.insert(Default::default());
+    let k2 = constraints.classes.insert(Default::default());
+    let mut calendar = ClassCalendar::default();
+    calendar
+      .add_one_class(DAY_MONDAY, TIMESLOT_10_00, k1)
+      .unwrap();
+    calendar
+      .add_one_class(DAY_MONDAY, TIMESLOT_19_00, k1)
+      .unwrap();
+    calendar
+      .add_one_class(DAY_FRIDAY, TIMESLOT_08_00, k2)
+      .unwrap();
+    let week_layout = WeekLayout::hourly(4, 9, 8).unwrap();
+    let calendar = calendar.with_week_layout(week_layout);
+    let t09_00 = Timeslot::from_usize(0).unwrap();
+    let t10_00 = Timeslot::from_usize(1).unwrap();
+    let t16_00 = Timeslot::from_usize(7).unwrap();
+    assert_eq!(calendar.get_count(DAY_MONDAY, t10_00, k1), 1);
+    assert_eq!(calendar.get_count(DAY_MONDAY, t16_00, k1), 1);
+    assert_eq!(calendar.get_count(DAY_THURSDAY, t09_00, k2), 1);
+    assert_eq!(calendar.class_entries().len(), 3);
+  }
+
+  #[test]
+  fn test_move_class_random_locked() {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let mut constraints = OptimizationConstraints::default();
+    let k1 = constraints.classes.insert(Default::default());
+    let k2 = constraints.classes.insert(Default::default());
+    let mut calendar = ClassCalendar::default();
+    calendar
+      .add_one_class(DAY_MONDAY, TIMESLOT_08_00, k1)
+      .unwrap();
+    calendar.set_locked(DAY_MONDAY, TIMESLOT_08_00, k1, true);
+    assert!(calendar.is_locked(DAY_MONDAY, TIMESLOT_08_00, k1));
+    assert!(matches!(
+      calendar.move_one_class_random(&mut rng),
+      Err(MoveOneClassRandomError::NoClassesToMove)
+    ));
+    calendar
+      .add_one_class(DAY_MONDAY, TIMESLOT_08_00, k2)
+      .unwrap();
+    for _ in 0..100 {
+      let delta = calendar.move_one_class_random(&mut rng).unwrap();
+      assert_eq!(delta.class_key, k2);
+    }
+    assert_eq!(calendar.get_count(DAY_MONDAY, TIMESLOT_08_00, k1), 1);
+
+    // Unlocked entries are removed first.
+    calendar
+      .add_one_class(DAY_MONDAY, TIMESLOT_08_00, k1)
+      .unwrap();
+    calendar.move_one_class(DAY_MONDAY, TIMESLOT_08_00, DAY_FRIDAY, TIMESLOT_10_00, k1);
+    assert!(calendar.is_locked(DAY_MONDAY, TIMESLOT_08_00, k1));
+    assert!(!calendar.is_locked(DAY_FRIDAY, TIMESLOT_10_00, k1));
+  }
 }
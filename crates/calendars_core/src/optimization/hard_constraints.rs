@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  classroom_assignment::count_classroom_assignment_collisions,
+  optimization::heuristics::{self, Heuristic},
+  ClassCalendar, ClassEntryDelta, OptimizationConstraints,
+};
+
+/// Violations of the rules that the optimizer never trades for a lower cost. A move that
+/// increases any of the categories is always rejected, even if it lowers another one, so
+/// once a schedule is feasible it stays feasible.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HardConstraintViolations {
+  /// Class hours placed where the professor is not available.
+  pub professor_not_available: u64,
+  /// Class hours that share a timeslot with another class of the same professor.
+  pub professor_double_booking: u64,
+  /// Class hours that couldn't get a classroom.
+  pub classroom_overflow: u64,
+}
+
+impl HardConstraintViolations {
+  pub fn count(state: &ClassCalendar, constraints: &OptimizationConstraints) -> Self {
+    Self {
+      professor_not_available: heuristics::count_not_available(state, constraints),
+      professor_double_booking: heuristics::same_timeslot_classes_count_per_professor(
+        state,
+        constraints,
+      ),
+      classroom_overflow: count_classroom_assignment_collisions(state, constraints) as u64,
+    }
+  }
+
+  /// Violations after `delta` turned `before`, which had `self` violations, into `after`.
+  pub(crate) fn with_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Self {
+    let apply = |count: u64, heuristic: &dyn Heuristic| {
+      count
+        .checked_add_signed(
+          heuristic
+            .evaluate_delta(before, after, constraints, delta)
+            .expect("Hard constraints are evaluated incrementally"),
+        )
+        .unwrap()
+    };
+    Self {
+      professor_not_available: apply(
+        self.professor_not_available,
+        &heuristics::ProfessorNotAvailable,
+      ),
+      professor_double_booking: apply(
+        self.professor_double_booking,
+        &heuristics::ProfessorSimultaneousClasses,
+      ),
+      classroom_overflow: apply(
+        self.classroom_overflow,
+        &heuristics::ClassroomAssignmentCollisions,
+      ),
+    }
+  }
+
+  pub fn total(&self) -> u64 {
+    self.professor_not_available + self.professor_double_booking + self.classroom_overflow
+  }
+
+  /// Whether no category has more violations than in `other`.
+  pub fn within(&self, other: &Self) -> bool {
+    self.professor_not_available <= other.professor_not_available
+      && self.professor_double_booking <= other.professor_double_booking
+      && self.classroom_overflow <= other.classroom_overflow
+  }
+
+  pub fn is_feasible(&self) -> bool {
+    self.total() == 0
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::{
+    Availability, SchoolSchedule, DAY_MONDAY, DAY_TUESDAY, TIMESLOT_08_00, TIMESLOT_09_00,
+  };
+
+  use super::*;
+
+  #[test]
+  fn test_hard_constraint_violations() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type_key = schedule.add_new_classroom_type();
+    schedule.add_new_classroom(classroom_type_key);
+    let professor_key = schedule.add_new_professor();
+    *schedule
+      .get_professor_mut(professor_key)
+      .unwrap()
+      .availability
+      .get_mut(DAY_MONDAY, TIMESLOT_08_00) = Availability::NotAvailable;
+    let k0 = schedule.add_new_class(professor_key);
+    let k1 = schedule.add_new_class(professor_key);
+    for class_key in [k0, k1] {
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_allowed_classroom_types([classroom_type_key].into());
+      class_entry.set_hours(1);
+    }
+    let constraints = schedule.get_simulation_constraints();
+
+    let mut state = schedule.class_calendar().clone();
+    let violations = HardConstraintViolations::count(&state, constraints);
+    assert_eq!(
+      violations,
+      HardConstraintViolations {
+        professor_not_available: 2,
+        professor_double_booking: 2,
+        classroom_overflow: 1,
+      }
+    );
+    assert!(!violations.is_feasible());
+
+    state.move_one_class(DAY_MONDAY, TIMESLOT_08_00, DAY_MONDAY, TIMESLOT_09_00, k0);
+    state.move_one_class(DAY_MONDAY, TIMESLOT_08_00, DAY_TUESDAY, TIMESLOT_08_00, k1);
+    assert!(HardConstraintViolations::count(&state, constraints).is_feasible());
+  }
+
+  #[test]
+  fn test_within() {
+    let violations = HardConstraintViolations {
+      professor_not_available: 1,
+      professor_double_booking: 2,
+      classroom_overflow: 0,
+    };
+    assert!(violations.within(&violations));
+    assert!(HardConstraintViolations::default().within(&violations));
+    // Trading a double booking for a classroom overflow keeps the total but is not allowed.
+    let traded = HardConstraintViolations {
+      professor_double_booking: 1,
+      classroom_overflow: 1,
+      ..violations
+    };
+    assert_eq!(traded.total(), violations.total());
+    assert!(!traded.within(&violations));
+  }
+}
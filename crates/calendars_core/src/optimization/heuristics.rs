@@ -1,182 +1,972 @@
+use std::collections::BTreeMap;
+
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use slotmap::SecondaryMap;
 use strum::{IntoEnumIterator, VariantArray};
 
+use crate::classroom_assignment::classroom_changes;
+use crate::classroom_assignment::count_classroom_assignment_collisions;
+use crate::classroom_assignment::count_classroom_changes;
+use crate::classroom_assignment::count_day_classroom_changes;
+use crate::classroom_assignment::count_timeslot_classroom_assignment_collisions;
+use crate::optimization::class_calendar::ClassEntryDelta;
 use crate::optimization::optimization_constraints::Availability;
 use crate::optimization::optimization_constraints::ClassKey;
-use crate::optimization::optimization_constraints::ClassroomType;
 use crate::optimization::optimization_constraints::OptimizationConstraints;
 use crate::optimization::optimization_constraints::ProfessorKey;
 use crate::optimization::optimization_constraints::Semester;
 use crate::week_calendar;
+use crate::week_calendar::WeekLayout;
 use crate::ClassCalendar;
 
+/// A term of the cost function of a schedule. Optimizers multiply the result of
+/// [`Heuristic::evaluate`] by a weight and add it to the total cost.
+pub trait Heuristic: Send + Sync {
+  fn name(&self) -> &str;
+
+  /// Number of violations in the schedule.
+  fn evaluate(&self, state: &ClassCalendar, constraints: &OptimizationConstraints) -> u64;
+
+  /// Change in [`Heuristic::evaluate`] caused by `delta`, which turned `before` into `after`.
+  /// Returning `None` makes the optimizer call [`Heuristic::evaluate`] on `after` instead.
+  fn evaluate_delta(
+    &self,
+    _before: &ClassCalendar,
+    _after: &ClassCalendar,
+    _constraints: &OptimizationConstraints,
+    _delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    None
+  }
+
+  /// The concrete occurrences behind [`Heuristic::evaluate`], for showing to the user.
+  fn violations(
+    &self,
+    _state: &ClassCalendar,
+    _constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    Vec::new()
+  }
+}
+
+/// Classes that break the rule of a heuristic, and the cells where they do.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+  /// [`Heuristic::name`] of the broken rule.
+  pub heuristic: String,
+  pub class_keys: Vec<ClassKey>,
+  pub cells: Vec<(week_calendar::Day, week_calendar::Timeslot)>,
+}
+
+impl Violation {
+  pub fn new(
+    heuristic: &(impl Heuristic + ?Sized),
+    class_keys: Vec<ClassKey>,
+    cells: Vec<(week_calendar::Day, week_calendar::Timeslot)>,
+  ) -> Self {
+    Self {
+      heuristic: heuristic.name().to_string(),
+      class_keys,
+      cells,
+    }
+  }
+}
+
+pub(crate) struct ClassroomAssignmentCollisions;
+
+impl Heuristic for ClassroomAssignmentCollisions {
+  fn name(&self) -> &str {
+    "classroom_assignment_collisions"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, constraints: &OptimizationConstraints) -> u64 {
+    count_classroom_assignment_collisions(state, constraints) as u64
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    iter_week(state.week_layout())
+      .filter(|(day, timeslot)| {
+        count_timeslot_classroom_assignment_collisions(state, constraints, *day, *timeslot) > 0
+      })
+      .map(|(day, timeslot)| {
+        Violation::new(
+          self,
+          classes_at(state, day, timeslot).collect(),
+          vec![(day, timeslot)],
+        )
+      })
+      .collect()
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      delta_cells(delta),
+      |state, (day, timeslot)| {
+        count_timeslot_classroom_assignment_collisions(state, constraints, day, timeslot)
+      },
+    ))
+  }
+}
+
+pub(crate) struct ProfessorSimultaneousClasses;
+
+impl Heuristic for ProfessorSimultaneousClasses {
+  fn name(&self) -> &str {
+    "professor_simultaneous_classes"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, constraints: &OptimizationConstraints) -> u64 {
+    same_timeslot_classes_count_per_professor(state, constraints)
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (day, timeslot) in iter_week(state.week_layout()) {
+      let mut professor_classes: BTreeMap<ProfessorKey, Vec<ClassKey>> = BTreeMap::new();
+      for class_key in classes_at(state, day, timeslot) {
+        let professor_key = constraints.classes.get(class_key).unwrap().professor_key;
+        professor_classes
+          .entry(professor_key)
+          .or_default()
+          .push(class_key);
+      }
+      for class_keys in professor_classes.into_values() {
+        if count_at(state, day, timeslot, &class_keys) >= 2 {
+          violations.push(Violation::new(self, class_keys, vec![(day, timeslot)]));
+        }
+      }
+    }
+    violations
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      delta_cells(delta),
+      |state, (day, timeslot)| {
+        same_timeslot_classes_count_per_professor_at(state, constraints, day, timeslot)
+      },
+    ))
+  }
+}
+
+pub(crate) struct SemesterSimultaneousClasses;
+
+impl Heuristic for SemesterSimultaneousClasses {
+  fn name(&self) -> &str {
+    "semester_simultaneous_classes"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, constraints: &OptimizationConstraints) -> u64 {
+    same_timeslot_classes_count_per_semester(state, constraints)
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (day, timeslot) in iter_week(state.week_layout()) {
+      for semester in Semester::iter() {
+        let class_keys = classes_at(state, day, timeslot)
+          .filter(|class_key| constraints.classes.get(*class_key).unwrap().semester == semester)
+          .collect_vec();
+        if count_at(state, day, timeslot, &class_keys) >= 2 {
+          violations.push(Violation::new(self, class_keys, vec![(day, timeslot)]));
+        }
+      }
+    }
+    violations
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      delta_cells(delta),
+      |state, (day, timeslot)| {
+        same_timeslot_classes_count_per_semester_at(state, constraints, day, timeslot)
+      },
+    ))
+  }
+}
+
+pub(crate) struct LabsOnDifferentDays;
+
+impl Heuristic for LabsOnDifferentDays {
+  fn name(&self) -> &str {
+    "labs_on_different_days"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, constraints: &OptimizationConstraints) -> u64 {
+    count_labs_on_different_days(state, constraints)
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    state
+      .iter_class_keys()
+      .filter(|class_key| {
+        count_labs_on_different_days_for_class(state, constraints, *class_key) > 0
+      })
+      .map(|class_key| Violation::new(self, vec![class_key], class_cells(state, class_key)))
+      .collect()
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      [delta.class_key],
+      |state, class_key| count_labs_on_different_days_for_class(state, constraints, class_key),
+    ))
+  }
+}
+
+pub(crate) struct ProfessorNotAvailable;
+
+impl Heuristic for ProfessorNotAvailable {
+  fn name(&self) -> &str {
+    "professor_not_available"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, constraints: &OptimizationConstraints) -> u64 {
+    count_not_available(state, constraints)
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    availability_violations(self, state, constraints, Availability::NotAvailable)
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      delta_cells(delta),
+      |state, (day, timeslot)| {
+        count_with_availability_at(
+          state,
+          constraints,
+          delta.class_key,
+          day,
+          timeslot,
+          Availability::NotAvailable,
+        )
+      },
+    ))
+  }
+}
+
+pub(crate) struct IncontinuousClasses;
+
+impl Heuristic for IncontinuousClasses {
+  fn name(&self) -> &str {
+    "incontinuous_classes"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, _constraints: &OptimizationConstraints) -> u64 {
+    count_incontinuous_classes(state)
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    _constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    state
+      .iter_class_keys()
+      .flat_map(|class_key| state.week_layout().days().map(move |day| (class_key, day)))
+      .filter(|(class_key, day)| count_incontinuous_classes_on_day(state, *class_key, *day) > 0)
+      .map(|(class_key, day)| {
+        Violation::new(
+          self,
+          vec![class_key],
+          class_cells(state, class_key)
+            .into_iter()
+            .filter(|(d, _t)| *d == day)
+            .collect(),
+        )
+      })
+      .collect()
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    _constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      delta_days(delta),
+      |state, day| count_incontinuous_classes_on_day(state, delta.class_key, day),
+    ))
+  }
+}
+
+pub(crate) struct OutsideSessionLength {
+  pub(crate) min_session_length: u8,
+  pub(crate) max_session_length: u8,
+}
+
+impl Heuristic for OutsideSessionLength {
+  fn name(&self) -> &str {
+    "outside_session_length"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, _constraints: &OptimizationConstraints) -> u64 {
+    count_outside_session_length(state, self.min_session_length, self.max_session_length)
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    _constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for class_key in state.iter_class_keys() {
+      for day in state.week_layout().days() {
+        for session in sessions_on_day(state, class_key, day) {
+          if is_outside_session_length(&session, self.min_session_length, self.max_session_length) {
+            violations.push(Violation::new(
+              self,
+              vec![class_key],
+              session
+                .into_iter()
+                .map(|timeslot| (day, timeslot))
+                .collect(),
+            ));
+          }
+        }
+      }
+    }
+    violations
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    _constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      delta_days(delta),
+      |state, day| {
+        count_outside_session_length_on_day(
+          state,
+          delta.class_key,
+          day,
+          self.min_session_length,
+          self.max_session_length,
+        )
+      },
+    ))
+  }
+}
+
+pub(crate) struct HolesPerSemester;
+
+impl Heuristic for HolesPerSemester {
+  fn name(&self) -> &str {
+    "holes_per_semester"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, constraints: &OptimizationConstraints) -> u64 {
+    count_holes_per_semester(state, constraints)
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for semester in Semester::iter() {
+      for day in state.week_layout().days() {
+        let holes = holes_on_day(state, constraints, semester, day);
+        if holes.is_empty() {
+          continue;
+        }
+        let class_keys = state
+          .iter_class_keys()
+          .filter(|class_key| {
+            constraints.classes.get(*class_key).unwrap().semester == semester
+              && state
+                .week_layout()
+                .timeslots()
+                .any(|timeslot| state.get_count(day, timeslot, *class_key) > 0)
+          })
+          .collect();
+        violations.push(Violation::new(
+          self,
+          class_keys,
+          holes.into_iter().map(|timeslot| (day, timeslot)).collect(),
+        ));
+      }
+    }
+    violations
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    let semester = constraints.classes.get(delta.class_key).unwrap().semester;
+    Some(local_delta(
+      before,
+      after,
+      delta_days(delta),
+      |state, day| count_holes_on_day(state, constraints, semester, day),
+    ))
+  }
+}
+
+pub(crate) struct ProfessorAvailableIfNeeded;
+
+impl Heuristic for ProfessorAvailableIfNeeded {
+  fn name(&self) -> &str {
+    "professor_available_if_needed"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, constraints: &OptimizationConstraints) -> u64 {
+    count_available_if_needed(state, constraints)
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    availability_violations(self, state, constraints, Availability::AvailableIfNeeded)
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      delta_cells(delta),
+      |state, (day, timeslot)| {
+        count_with_availability_at(
+          state,
+          constraints,
+          delta.class_key,
+          day,
+          timeslot,
+          Availability::AvailableIfNeeded,
+        )
+      },
+    ))
+  }
+}
+
+pub(crate) struct InconsistentClassTimeslots;
+
+impl Heuristic for InconsistentClassTimeslots {
+  fn name(&self) -> &str {
+    "inconsistent_class_timeslots"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, _constraints: &OptimizationConstraints) -> u64 {
+    count_inconsistent_class_timeslots(state)
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    _constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    state
+      .iter_class_keys()
+      .map(|class_key| (class_key, inconsistent_class_cells(state, class_key)))
+      .filter(|(_class_key, cells)| !cells.is_empty())
+      .map(|(class_key, cells)| Violation::new(self, vec![class_key], cells))
+      .collect()
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    _constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      [delta.class_key],
+      count_inconsistent_class_timeslots_for_class,
+    ))
+  }
+}
+
+/// The classroom assignment of a day doesn't depend on the other days, so only the days a move
+/// touches are assigned again.
+pub(crate) struct ClassroomChanges;
+
+impl Heuristic for ClassroomChanges {
+  fn name(&self) -> &str {
+    "classroom_changes"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, constraints: &OptimizationConstraints) -> u64 {
+    count_classroom_changes(state, constraints)
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      delta_days(delta),
+      |state, day| count_day_classroom_changes(state, constraints, day),
+    ))
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    classroom_changes(state, constraints)
+      .into_iter()
+      .map(|(k0, k1)| {
+        Violation::new(
+          self,
+          vec![k1.class_key],
+          vec![(k0.day, k0.timeslot), (k1.day, k1.timeslot)],
+        )
+      })
+      .collect()
+  }
+}
+
+pub(crate) struct SimultaneousClasses;
+
+impl Heuristic for SimultaneousClasses {
+  fn name(&self) -> &str {
+    "simultaneous_classes"
+  }
+
+  fn evaluate(&self, state: &ClassCalendar, _constraints: &OptimizationConstraints) -> u64 {
+    same_timeslot_classes_count(state)
+  }
+
+  fn violations(
+    &self,
+    state: &ClassCalendar,
+    _constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    iter_week(state.week_layout())
+      .filter(|(day, timeslot)| same_timeslot_classes_count_at(state, *day, *timeslot) > 0)
+      .map(|(day, timeslot)| {
+        Violation::new(
+          self,
+          classes_at(state, day, timeslot).collect(),
+          vec![(day, timeslot)],
+        )
+      })
+      .collect()
+  }
+
+  fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    _constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    Some(local_delta(
+      before,
+      after,
+      delta_cells(delta),
+      |state, (day, timeslot)| same_timeslot_classes_count_at(state, day, timeslot),
+    ))
+  }
+}
+
+/// Cells whose contents change with `delta`.
+fn delta_cells(
+  delta: &ClassEntryDelta,
+) -> impl Iterator<Item = (week_calendar::Day, week_calendar::Timeslot)> {
+  [
+    (delta.src_day, delta.src_timeslot),
+    (delta.dst_day, delta.dst_timeslot),
+  ]
+  .into_iter()
+  .unique()
+}
+
+/// Days whose contents change with `delta`.
+fn delta_days(delta: &ClassEntryDelta) -> impl Iterator<Item = week_calendar::Day> {
+  [delta.src_day, delta.dst_day].into_iter().unique()
+}
+
+/// Change of a heuristic that adds up `count` over parts of the schedule, given the only
+/// parts that differ between `before` and `after`.
+fn local_delta<T: Copy>(
+  before: &ClassCalendar,
+  after: &ClassCalendar,
+  changed: impl IntoIterator<Item = T>,
+  count: impl Fn(&ClassCalendar, T) -> u64,
+) -> i64 {
+  changed
+    .into_iter()
+    .map(|x| count(after, x) as i64 - count(before, x) as i64)
+    .sum()
+}
+
+fn classes_at(
+  state: &ClassCalendar,
+  day: week_calendar::Day,
+  timeslot: week_calendar::Timeslot,
+) -> impl Iterator<Item = ClassKey> + '_ {
+  state
+    .iter_class_keys()
+    .filter(move |class_key| state.get_count(day, timeslot, *class_key) > 0)
+}
+
+fn count_at(
+  state: &ClassCalendar,
+  day: week_calendar::Day,
+  timeslot: week_calendar::Timeslot,
+  class_keys: &[ClassKey],
+) -> u64 {
+  class_keys
+    .iter()
+    .map(|class_key| state.get_count(day, timeslot, *class_key) as u64)
+    .sum()
+}
+
+fn class_cells(
+  state: &ClassCalendar,
+  class_key: ClassKey,
+) -> Vec<(week_calendar::Day, week_calendar::Timeslot)> {
+  iter_week(state.week_layout())
+    .filter(|(day, timeslot)| state.get_count(*day, *timeslot, class_key) > 0)
+    .collect()
+}
+
+fn availability_violations(
+  heuristic: &impl Heuristic,
+  state: &ClassCalendar,
+  constraints: &OptimizationConstraints,
+  availability: Availability,
+) -> Vec<Violation> {
+  iter_class_calendar(state)
+    .filter(|(class_key, day, timeslot)| {
+      count_with_availability_at(
+        state,
+        constraints,
+        *class_key,
+        *day,
+        *timeslot,
+        availability,
+      ) > 0
+    })
+    .map(|(class_key, day, timeslot)| {
+      Violation::new(heuristic, vec![class_key], vec![(day, timeslot)])
+    })
+    .collect()
+}
+
 fn iter_class_calendar(
   class_calendar: &ClassCalendar,
 ) -> impl Iterator<Item = (ClassKey, week_calendar::Day, week_calendar::Timeslot)> + '_ {
   let classes = class_calendar.iter_class_keys();
+  let week_layout = class_calendar.week_layout();
 
   classes.flat_map(move |class_key| {
-    week_calendar::Day::all().flat_map(move |day| {
-      week_calendar::Timeslot::all().map(move |timeslot| (class_key, day, timeslot))
+    week_layout.days().flat_map(move |day| {
+      week_layout
+        .timeslots()
+        .map(move |timeslot| (class_key, day, timeslot))
     })
   })
 }
 
-fn iter_week() -> impl Iterator<Item = (week_calendar::Day, week_calendar::Timeslot)> {
-  week_calendar::Day::all().flat_map(|d| week_calendar::Timeslot::all().map(move |t| (d, t)))
+fn iter_week(
+  week_layout: &WeekLayout,
+) -> impl Iterator<Item = (week_calendar::Day, week_calendar::Timeslot)> + '_ {
+  week_layout
+    .days()
+    .flat_map(|d| week_layout.timeslots().map(move |t| (d, t)))
 }
 
 pub(crate) fn count_holes_per_semester(
   state: &ClassCalendar,
   simulation_constraints: &OptimizationConstraints,
 ) -> u64 {
-  let mut total: u64 = 0;
-  for semester in Semester::iter() {
-    for day in week_calendar::Day::all() {
-      let has_class = week_calendar::Timeslot::all()
-        .map(|t| {
-          for class_key in state.iter_class_keys() {
-            if state.get_count(day, t, class_key) >= 1
-              && simulation_constraints
-                .classes
-                .get(class_key)
-                .unwrap()
-                .semester
-                == semester
-            {
-              return true;
-            }
-          }
-          false
-        })
-        .collect_vec();
-      let first_class_i = has_class.iter().position(|x| *x);
-      let last_class_i = has_class
-        .iter()
-        .enumerate()
-        .rev()
-        .find(|(_i, &x)| x)
-        .map(|(i, _x)| i);
-      if let Some(first_class_i) = first_class_i {
-        let last_class_i = last_class_i.expect(
-          "If we found a class from the beginning, we should've also found a class from the back.",
-        );
-        total += has_class
-          .get(first_class_i..=last_class_i)
-          .unwrap()
-          .iter()
-          .filter(|&x| !x)
-          .count() as u64;
+  Semester::iter()
+    .flat_map(|semester| state.week_layout().days().map(move |day| (semester, day)))
+    .map(|(semester, day)| count_holes_on_day(state, simulation_constraints, semester, day))
+    .sum()
+}
+
+fn count_holes_on_day(
+  state: &ClassCalendar,
+  simulation_constraints: &OptimizationConstraints,
+  semester: Semester,
+  day: week_calendar::Day,
+) -> u64 {
+  holes_on_day(state, simulation_constraints, semester, day).len() as u64
+}
+
+/// Empty timeslots of a semester between its first and last class of the day.
+fn holes_on_day(
+  state: &ClassCalendar,
+  simulation_constraints: &OptimizationConstraints,
+  semester: Semester,
+  day: week_calendar::Day,
+) -> Vec<week_calendar::Timeslot> {
+  let timeslots = state.week_layout().timeslots().collect_vec();
+  let has_class = timeslots
+    .iter()
+    .map(|t| {
+      for class_key in state.iter_class_keys() {
+        if state.get_count(day, *t, class_key) >= 1
+          && simulation_constraints
+            .classes
+            .get(class_key)
+            .unwrap()
+            .semester
+            == semester
+        {
+          return true;
+        }
       }
-    }
+      false
+    })
+    .collect_vec();
+  let first_class_i = has_class.iter().position(|x| *x);
+  let last_class_i = has_class
+    .iter()
+    .enumerate()
+    .rev()
+    .find(|(_i, &x)| x)
+    .map(|(i, _x)| i);
+  if let Some(first_class_i) = first_class_i {
+    let last_class_i = last_class_i.expect(
+      "If we found a class from the beginning, we should've also found a class from the back.",
+    );
+    (first_class_i..=last_class_i)
+      .filter(|i| !has_class[*i])
+      .map(|i| timeslots[i])
+      .collect()
+  } else {
+    Vec::new()
   }
-  total
 }
 
 pub(crate) fn same_timeslot_classes_count_per_professor(
   state: &ClassCalendar,
   simulation_constraints: &OptimizationConstraints,
 ) -> u64 {
-  let mut same_timeslot_classes_count: u64 = 0;
-  let mut professor_class_counter: SecondaryMap<ProfessorKey, u64> =
-    SecondaryMap::from_iter(simulation_constraints.professors.keys().map(|k| (k, 0)));
-  for (day, timeslot) in iter_week() {
-    professor_class_counter.values_mut().for_each(|x| *x = 0);
-    for class_key in state.iter_class_keys() {
-      let count = state.get_count(day, timeslot, class_key);
-      let class = simulation_constraints.classes.get(class_key).unwrap();
-      let professor_key = class.professor_key;
-      professor_class_counter[professor_key] += count as u64;
+  iter_week(state.week_layout())
+    .map(|(day, timeslot)| {
+      same_timeslot_classes_count_per_professor_at(state, simulation_constraints, day, timeslot)
+    })
+    .sum()
+}
+
+fn same_timeslot_classes_count_per_professor_at(
+  state: &ClassCalendar,
+  simulation_constraints: &OptimizationConstraints,
+  day: week_calendar::Day,
+  timeslot: week_calendar::Timeslot,
+) -> u64 {
+  let mut professor_class_counter: SecondaryMap<ProfessorKey, u64> = SecondaryMap::new();
+  for class_key in state.iter_class_keys() {
+    let count = state.get_count(day, timeslot, class_key);
+    if count == 0 {
+      continue;
     }
-    same_timeslot_classes_count += professor_class_counter
-      .iter()
-      .map(|(_k, &v)| v)
-      .filter(|&x| x >= 2)
-      .sum::<u64>();
+    let class = simulation_constraints.classes.get(class_key).unwrap();
+    let professor_key = class.professor_key;
+    *professor_class_counter
+      .entry(professor_key)
+      .unwrap()
+      .or_default() += count as u64;
   }
-  same_timeslot_classes_count
+  professor_class_counter
+    .values()
+    .filter(|&&x| x >= 2)
+    .sum::<u64>()
 }
 
 pub(crate) fn same_timeslot_classes_count_per_semester(
   state: &ClassCalendar,
   simulation_constraints: &OptimizationConstraints,
 ) -> u64 {
-  let mut same_timeslot_classes_count: u64 = 0;
+  iter_week(state.week_layout())
+    .map(|(day, timeslot)| {
+      same_timeslot_classes_count_per_semester_at(state, simulation_constraints, day, timeslot)
+    })
+    .sum()
+}
+
+pub(crate) fn same_timeslot_classes_count_per_semester_at(
+  state: &ClassCalendar,
+  simulation_constraints: &OptimizationConstraints,
+  day: week_calendar::Day,
+  timeslot: week_calendar::Timeslot,
+) -> u64 {
   let mut semester_class_counter = [0_u64; Semester::VARIANTS.len()];
-  for (day, timeslot) in iter_week() {
-    semester_class_counter.fill(0);
-    for class_key in state.iter_class_keys() {
-      let count = state.get_count(day, timeslot, class_key);
-      let class = simulation_constraints.classes.get(class_key).unwrap();
-      let semester = class.semester;
-      semester_class_counter[Semester::VARIANTS
-        .iter()
-        .position(|v| v == &semester)
-        .unwrap()] += count as u64;
-    }
-    same_timeslot_classes_count += semester_class_counter
+  for class_key in state.iter_class_keys() {
+    let count = state.get_count(day, timeslot, class_key);
+    let class = simulation_constraints.classes.get(class_key).unwrap();
+    let semester = class.semester;
+    semester_class_counter[Semester::VARIANTS
       .iter()
-      .filter(|x| **x >= 2)
-      .sum::<u64>();
+      .position(|v| v == &semester)
+      .unwrap()] += count as u64;
   }
-  same_timeslot_classes_count
+  semester_class_counter
+    .iter()
+    .filter(|x| **x >= 2)
+    .sum::<u64>()
 }
 
 pub(crate) fn same_timeslot_classes_count(state: &ClassCalendar) -> u64 {
-  let mut same_timeslot_classes_count: u64 = 0;
-  for (day, timeslot) in iter_week() {
-    let mut x: u64 = 0;
-    for class_key in state.iter_class_keys() {
-      x += state.get_count(day, timeslot, class_key) as u64;
-    }
-    if x >= 2 {
-      same_timeslot_classes_count += x;
-    }
+  iter_week(state.week_layout())
+    .map(|(day, timeslot)| same_timeslot_classes_count_at(state, day, timeslot))
+    .sum()
+}
+
+pub(crate) fn same_timeslot_classes_count_at(
+  state: &ClassCalendar,
+  day: week_calendar::Day,
+  timeslot: week_calendar::Timeslot,
+) -> u64 {
+  let mut x: u64 = 0;
+  for class_key in state.iter_class_keys() {
+    x += state.get_count(day, timeslot, class_key) as u64;
+  }
+  if x >= 2 {
+    x
+  } else {
+    0
   }
-  same_timeslot_classes_count
 }
 
 pub(crate) fn count_not_available(
   state: &ClassCalendar,
   constraints: &OptimizationConstraints,
 ) -> u64 {
-  let mut not_available_count: u64 = 0;
-
-  for (class_key, day, timeslot) in iter_class_calendar(state) {
-    let class = constraints.classes.get(class_key).unwrap();
-    let professor_key = class.professor_key;
-    let professor = &constraints.professors[professor_key];
-    let availability = professor.availability.get(day, timeslot);
-    if matches!(availability, Availability::NotAvailable)
-      && (state.get_count(day, timeslot, class_key) > 0)
-    {
-      not_available_count += 1;
-    }
-  }
-
-  not_available_count
+  iter_class_calendar(state)
+    .map(|(class_key, day, timeslot)| {
+      count_with_availability_at(
+        state,
+        constraints,
+        class_key,
+        day,
+        timeslot,
+        Availability::NotAvailable,
+      )
+    })
+    .sum()
 }
 
 pub(crate) fn count_available_if_needed(
   state: &ClassCalendar,
   constraints: &OptimizationConstraints,
 ) -> u64 {
-  let mut available_if_needed_count: u64 = 0;
-
-  for (class_key, day, timeslot) in iter_class_calendar(state) {
-    let class = constraints.classes.get(class_key).unwrap();
-    let professor_key = class.professor_key;
-    let professor = &constraints.professors[professor_key];
-    let availability = professor.availability.get(day, timeslot);
-    if matches!(availability, Availability::AvailableIfNeeded)
-      && (state.get_count(day, timeslot, class_key) > 0)
-    {
-      available_if_needed_count += 1;
-    }
-  }
+  iter_class_calendar(state)
+    .map(|(class_key, day, timeslot)| {
+      count_with_availability_at(
+        state,
+        constraints,
+        class_key,
+        day,
+        timeslot,
+        Availability::AvailableIfNeeded,
+      )
+    })
+    .sum()
+}
 
-  available_if_needed_count
+/// 1 if the class is in the timeslot and its professor has the given availability there.
+fn count_with_availability_at(
+  state: &ClassCalendar,
+  constraints: &OptimizationConstraints,
+  class_key: ClassKey,
+  day: week_calendar::Day,
+  timeslot: week_calendar::Timeslot,
+  availability: Availability,
+) -> u64 {
+  let class = constraints.classes.get(class_key).unwrap();
+  let professor_key = class.professor_key;
+  let professor = &constraints.professors[professor_key];
+  (professor.availability.get(day, timeslot) == &availability
+    && state.get_count(day, timeslot, class_key) > 0) as u64
 }
 
 pub(crate) fn count_outside_session_length(
@@ -184,121 +974,165 @@ pub(crate) fn count_outside_session_length(
   min_session_length: u8,
   max_session_length: u8,
 ) -> u64 {
-  let mut outside_session_length_count: u64 = 0;
-  for class_key in state.iter_class_keys() {
-    for day in week_calendar::Day::all() {
-      let mut session_length: u8 = 0;
-      for timeslot in week_calendar::Timeslot::all() {
-        if state.get_count(day, timeslot, class_key) > 0 {
-          session_length += 1;
-        } else if session_length > 0 {
-          if session_length < min_session_length || max_session_length < session_length {
-            outside_session_length_count += 1;
-          }
-          session_length = 0;
-        } else {
-          assert_eq!(session_length, 0);
-        }
-      }
-      if session_length >= 1
-        && (session_length < min_session_length || max_session_length < session_length)
-      {
-        outside_session_length_count += 1;
-      }
+  state
+    .iter_class_keys()
+    .flat_map(|class_key| state.week_layout().days().map(move |day| (class_key, day)))
+    .map(|(class_key, day)| {
+      count_outside_session_length_on_day(
+        state,
+        class_key,
+        day,
+        min_session_length,
+        max_session_length,
+      )
+    })
+    .sum()
+}
+
+fn count_outside_session_length_on_day(
+  state: &ClassCalendar,
+  class_key: ClassKey,
+  day: week_calendar::Day,
+  min_session_length: u8,
+  max_session_length: u8,
+) -> u64 {
+  sessions_on_day(state, class_key, day)
+    .iter()
+    .filter(|session| is_outside_session_length(session, min_session_length, max_session_length))
+    .count() as u64
+}
+
+fn is_outside_session_length(
+  session: &[week_calendar::Timeslot],
+  min_session_length: u8,
+  max_session_length: u8,
+) -> bool {
+  session.len() < min_session_length as usize || (max_session_length as usize) < session.len()
+}
+
+/// Runs of contiguous timeslots in which the class is present during the day.
+fn sessions_on_day(
+  state: &ClassCalendar,
+  class_key: ClassKey,
+  day: week_calendar::Day,
+) -> Vec<Vec<week_calendar::Timeslot>> {
+  let mut sessions = Vec::new();
+  let mut session = Vec::new();
+  for timeslot in state.week_layout().timeslots() {
+    if state.get_count(day, timeslot, class_key) > 0 {
+      session.push(timeslot);
+    } else if !session.is_empty() {
+      sessions.push(std::mem::take(&mut session));
     }
   }
-  outside_session_length_count
+  if !session.is_empty() {
+    sessions.push(session);
+  }
+  sessions
 }
 
 pub(crate) fn count_inconsistent_class_timeslots(state: &ClassCalendar) -> u64 {
-  let mut class_days: SecondaryMap<ClassKey, u8> = Default::default(); // Counts the number of days in which the i-th class is present
-  for class_key in state.iter_class_keys() {
-    class_days.insert(class_key, 0);
-  }
-  for day in week_calendar::Day::all() {
-    for class_key in state.iter_class_keys() {
-      let mut class_found: bool = false;
-      for timeslot in week_calendar::Timeslot::all() {
-        if state.get_count(day, timeslot, class_key) > 0 {
-          class_found = true;
-          break;
-        }
-      }
-      if class_found {
-        class_days[class_key] += 1;
-      }
-    }
+  state
+    .iter_class_keys()
+    .map(|class_key| count_inconsistent_class_timeslots_for_class(state, class_key))
+    .sum()
+}
+
+fn count_inconsistent_class_timeslots_for_class(state: &ClassCalendar, class_key: ClassKey) -> u64 {
+  inconsistent_class_cells(state, class_key).len() as u64
+}
+
+/// Cells of a class that meets on several days, in timeslots used on only one of them.
+fn inconsistent_class_cells(
+  state: &ClassCalendar,
+  class_key: ClassKey,
+) -> Vec<(week_calendar::Day, week_calendar::Timeslot)> {
+  // Number of days in which the class is present
+  let class_days = state
+    .week_layout()
+    .days()
+    .filter(|day| {
+      state
+        .week_layout()
+        .timeslots()
+        .any(|timeslot| state.get_count(*day, timeslot, class_key) > 0)
+    })
+    .count();
+  if class_days < 2 {
+    return Vec::new();
   }
 
-  let mut inconsistent_count = 0;
-  for class_key in state.iter_class_keys() {
-    if class_days[class_key] < 2 {
-      continue;
-    }
-    for timeslot in week_calendar::Timeslot::all() {
-      let mut count = 0;
-      for day in week_calendar::Day::all() {
-        if state.get_count(day, timeslot, class_key) > 0 {
-          count += 1;
-        }
-      }
-      if count == 1 {
-        inconsistent_count += 1;
-      }
+  let mut inconsistent_cells = Vec::new();
+  for timeslot in state.week_layout().timeslots() {
+    let days = state
+      .week_layout()
+      .days()
+      .filter(|day| state.get_count(*day, timeslot, class_key) > 0)
+      .collect_vec();
+    if let [day] = days[..] {
+      inconsistent_cells.push((day, timeslot));
     }
   }
-
-  inconsistent_count
+  inconsistent_cells
 }
 
 pub(crate) fn count_labs_on_different_days(
   state: &ClassCalendar,
   constraints: &OptimizationConstraints,
 ) -> u64 {
-  let mut different_days_labs_count = 0;
-  let class_keys = state.iter_class_keys();
-  for (class_key, class) in class_keys.map(|k| (k, constraints.classes.get(k).unwrap())) {
-    if class
-      .allowed_classroom_types
-      .intersection_c(ClassroomType::LabFisica | ClassroomType::LabQuimica)
-      .is_empty()
+  state
+    .iter_class_keys()
+    .map(|class_key| count_labs_on_different_days_for_class(state, constraints, class_key))
+    .sum()
+}
+
+fn count_labs_on_different_days_for_class(
+  state: &ClassCalendar,
+  constraints: &OptimizationConstraints,
+  class_key: ClassKey,
+) -> u64 {
+  let class = constraints.classes.get(class_key).unwrap();
+  if !constraints.is_lab(class) {
+    return 0;
+  }
+  let mut count: u64 = 0;
+  for day in state.week_layout().days() {
+    if state
+      .week_layout()
+      .timeslots()
+      .map(|timeslot| state.get_count(day, timeslot, class_key))
+      .any(|c| c >= 1)
     {
-      continue;
-    }
-    let mut count: u64 = 0;
-    for day in week_calendar::Day::all() {
-      if week_calendar::Timeslot::all()
-        .map(|timeslot| state.get_count(day, timeslot, class_key))
-        .any(|c| c >= 1)
-      {
-        count += 1;
-      }
-    }
-    if count >= 2 {
-      different_days_labs_count += count - 1;
+      count += 1;
     }
   }
-  different_days_labs_count
+  count.saturating_sub(1)
 }
 
 pub(crate) fn count_incontinuous_classes(state: &ClassCalendar) -> u64 {
-  let mut count = 0;
-  for class_key in state.iter_class_keys() {
-    for day in week_calendar::Day::all() {
-      let times = week_calendar::Timeslot::all()
-        .enumerate()
-        .map(|(i, t)| (i, state.get_count(day, t, class_key)))
-        .filter(|(_i, c)| *c >= 1)
-        .map(|(i, _c)| i);
-      if times.tuple_windows().any(|(i1, i2)| {
-        assert!(i1 < i2);
-        i1 + 1 < i2
-      }) {
-        count += 1;
-      }
-    }
-  }
-  count
+  state
+    .iter_class_keys()
+    .flat_map(|class_key| state.week_layout().days().map(move |day| (class_key, day)))
+    .map(|(class_key, day)| count_incontinuous_classes_on_day(state, class_key, day))
+    .sum()
+}
+
+fn count_incontinuous_classes_on_day(
+  state: &ClassCalendar,
+  class_key: ClassKey,
+  day: week_calendar::Day,
+) -> u64 {
+  let times = state
+    .week_layout()
+    .timeslots()
+    .enumerate()
+    .map(|(i, t)| (i, state.get_count(day, t, class_key)))
+    .filter(|(_i, c)| *c >= 1)
+    .map(|(i, _c)| i);
+  times.tuple_windows().any(|(i1, i2)| {
+    assert!(i1 < i2);
+    i1 + 1 < i2
+  }) as u64
 }
 
 #[cfg(test)]
@@ -311,6 +1145,9 @@ mod test {
     Day, Timeslot, DAY_MONDAY, DAY_TUESDAY, DAY_WEDNESDAY,
   };
 
+  use rand::{seq::SliceRandom, SeedableRng};
+  use rand_chacha::ChaCha8Rng;
+
   use self::week_calendar::TIMESLOT_08_00;
 
   use super::*;
@@ -382,19 +1219,24 @@ mod test {
 
   #[test]
   fn count_labs_on_different_days_test() {
-    // TODO: Where did the asserts go from this test?
     let mut schedule = SchoolSchedule::default();
+    let classroom_type = schedule.add_new_classroom_type();
+    let lab_classroom_type = schedule.add_new_classroom_type();
+    schedule
+      .get_classroom_type_mut(lab_classroom_type)
+      .unwrap()
+      .lab = true;
     let p0 = schedule.add_new_professor();
     let k0 = schedule.add_new_class(p0);
     schedule
       .get_class_entry(k0)
       .unwrap()
-      .set_allowed_classroom_types(ClassroomType::AulaSimple | ClassroomType::AulaDoble);
+      .set_allowed_classroom_types([classroom_type].into());
     let k1 = schedule.add_new_class(p0);
     schedule
       .get_class_entry(k1)
       .unwrap()
-      .set_allowed_classroom_types(ClassroomType::LabFisica | ClassroomType::LabQuimica);
+      .set_allowed_classroom_types([lab_classroom_type].into());
     let mut state = schedule.class_calendar().clone();
     schedule.get_class_entry(k0).unwrap().set_hours(3);
     schedule.get_class_entry(k1).unwrap().set_hours(3);
@@ -410,6 +1252,13 @@ mod test {
     state.add_one_class(DAY_MONDAY, TIMESLOT_11_00, k1).unwrap();
     state.move_one_class(DAY_MONDAY, TIMESLOT_09_00, DAY_TUESDAY, TIMESLOT_08_00, k1);
     schedule.replace_class_calendar(state).unwrap();
+    assert_eq!(
+      count_labs_on_different_days(
+        schedule.class_calendar(),
+        schedule.get_simulation_constraints()
+      ),
+      1
+    );
   }
 
   #[test]
@@ -471,4 +1320,143 @@ mod test {
       2
     );
   }
+
+  fn random_schedule(rng: &mut impl rand::Rng) -> SchoolSchedule {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type = schedule.add_new_classroom_type();
+    let lab_classroom_type = schedule.add_new_classroom_type();
+    schedule
+      .get_classroom_type_mut(lab_classroom_type)
+      .unwrap()
+      .lab = true;
+    schedule.add_new_classroom(classroom_type);
+    schedule.add_new_classroom(lab_classroom_type);
+    let week_layout = schedule.week_layout().clone();
+    let professor_keys = [schedule.add_new_professor(), schedule.add_new_professor()];
+    for professor_key in professor_keys {
+      for (day, timeslot) in iter_week(&week_layout) {
+        *schedule
+          .get_professor_mut(professor_key)
+          .unwrap()
+          .availability
+          .get_mut(day, timeslot) = *[
+          Availability::Available,
+          Availability::AvailableIfNeeded,
+          Availability::NotAvailable,
+        ]
+        .choose(rng)
+        .unwrap();
+      }
+    }
+    for (i, semester) in [Semester::S1, Semester::S1, Semester::S2, Semester::S3]
+      .into_iter()
+      .enumerate()
+    {
+      let class_key = schedule.add_new_class(professor_keys[i % 2]);
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_semester(semester);
+      class_entry.set_allowed_classroom_types([[classroom_type, lab_classroom_type][i % 2]].into());
+      class_entry.set_hours(4);
+    }
+    schedule
+  }
+
+  fn built_in_heuristics() -> [Box<dyn Heuristic>; 12] {
+    [
+      Box::new(ClassroomAssignmentCollisions),
+      Box::new(ProfessorSimultaneousClasses),
+      Box::new(SemesterSimultaneousClasses),
+      Box::new(LabsOnDifferentDays),
+      Box::new(ProfessorNotAvailable),
+      Box::new(IncontinuousClasses),
+      Box::new(OutsideSessionLength {
+        min_session_length: 2,
+        max_session_length: 4,
+      }),
+      Box::new(HolesPerSemester),
+      Box::new(ProfessorAvailableIfNeeded),
+      Box::new(InconsistentClassTimeslots),
+      Box::new(ClassroomChanges),
+      Box::new(SimultaneousClasses),
+    ]
+  }
+
+  #[test]
+  fn test_evaluate_delta() {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let schedule = random_schedule(&mut rng);
+    let constraints = schedule.get_simulation_constraints();
+    let heuristics = built_in_heuristics();
+
+    let mut state = schedule.class_calendar().clone();
+    for _ in 0..500 {
+      let before = state.clone();
+      let delta = state.move_one_class_random(&mut rng).unwrap();
+      for heuristic in heuristics.iter() {
+        let expected = Some(
+          heuristic.evaluate(&state, constraints) as i64
+            - heuristic.evaluate(&before, constraints) as i64,
+        );
+        assert_eq!(
+          heuristic.evaluate_delta(&before, &state, constraints, &delta),
+          expected,
+          "{}",
+          heuristic.name()
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn test_violations() {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let schedule = random_schedule(&mut rng);
+    let constraints = schedule.get_simulation_constraints();
+    let heuristics = built_in_heuristics();
+
+    let mut state = schedule.class_calendar().clone();
+    for _ in 0..100 {
+      state.move_one_class_random(&mut rng).unwrap();
+      for heuristic in heuristics.iter() {
+        let violations = heuristic.violations(&state, constraints);
+        assert_eq!(
+          violations.is_empty(),
+          heuristic.evaluate(&state, constraints) == 0,
+          "{}",
+          heuristic.name()
+        );
+        assert!(violations
+          .iter()
+          .all(|violation| violation.heuristic == heuristic.name()));
+      }
+    }
+  }
+
+  #[test]
+  fn test_not_available_violations() {
+    let mut schedule = SchoolSchedule::default();
+    let p0 = schedule.add_new_professor();
+    *schedule
+      .get_professor_mut(p0)
+      .unwrap()
+      .availability
+      .get_mut(DAY_MONDAY, TIMESLOT_09_00) = Availability::NotAvailable;
+    let k0 = schedule.add_new_class(p0);
+    let mut state = schedule.class_calendar().clone();
+    schedule.get_class_entry(k0).unwrap().set_hours(2);
+    state.add_one_class(DAY_MONDAY, TIMESLOT_08_00, k0).unwrap();
+    state.add_one_class(DAY_MONDAY, TIMESLOT_09_00, k0).unwrap();
+    schedule.replace_class_calendar(state).unwrap();
+    assert_eq!(
+      ProfessorNotAvailable.violations(
+        schedule.class_calendar(),
+        schedule.get_simulation_constraints()
+      ),
+      vec![Violation {
+        heuristic: "professor_not_available".to_string(),
+        class_keys: vec![k0],
+        cells: vec![(DAY_MONDAY, TIMESLOT_09_00)],
+      }]
+    );
+  }
 }
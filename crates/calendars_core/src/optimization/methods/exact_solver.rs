@@ -0,0 +1,414 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use slotmap::SecondaryMap;
+
+use crate::{
+  classroom_assignment::count_timeslot_classroom_assignment_collisions,
+  optimization::heuristics::{
+    same_timeslot_classes_count_at, same_timeslot_classes_count_per_semester_at,
+  },
+  week_calendar, Availability, ClassCalendar, ClassKey, HardConstraintViolations, HeuristicCost,
+  HeuristicWeights, OptimizationConstraints, ProfessorKey,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExactSolverOptions {
+  pub initial_state: ClassCalendar,
+  /// Classes to schedule. The rest keep their timeslots from `initial_state`, like locked
+  /// entries do. `None` schedules every class.
+  #[serde(default)]
+  pub classes: Option<Vec<ClassKey>>,
+  /// Search nodes to visit before giving up on proving optimality.
+  pub max_nodes: u64,
+  #[serde(default)]
+  pub heuristic_weights: HeuristicWeights,
+}
+
+impl Default for ExactSolverOptions {
+  fn default() -> Self {
+    Self {
+      initial_state: Default::default(),
+      classes: None,
+      max_nodes: 1_000_000,
+      heuristic_weights: Default::default(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExactSolution {
+  pub calendar: ClassCalendar,
+  /// Weighted count of simultaneous classes, simultaneous classes of a semester and hours
+  /// where the professor is only available if needed. The other heuristics depend on the
+  /// shape of whole sessions and are not optimized.
+  pub cost: u64,
+  /// Whether no schedule has a lower `cost`. Only `cost` is minimized, so the rest of the
+  /// heuristics in `cost_breakdown` can still be worse than in other schedules. `false` if the
+  /// node limit was reached before the search could prove it.
+  pub optimal: bool,
+  /// Cost of `calendar` under every heuristic, with the weights of the options.
+  pub cost_breakdown: Vec<HeuristicCost>,
+}
+
+/// Why no schedule satisfies the hard constraints.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ExactSolverError {
+  #[error("The classes that can't be moved already violate the hard constraints: {0:?}")]
+  FixedClassesConflict(HardConstraintViolations),
+  #[error(
+    "Class {class_key:?} has {hours} hours but only {available_timeslots} timeslots where its professor is available, free and a classroom fits"
+  )]
+  NotEnoughTimeslots {
+    class_key: ClassKey,
+    hours: usize,
+    available_timeslots: usize,
+  },
+  #[error(
+    "Professor {professor_key:?} has {hours} class hours but only {available_timeslots} timeslots where they can teach"
+  )]
+  ProfessorOverbooked {
+    professor_key: ProfessorKey,
+    hours: usize,
+    available_timeslots: usize,
+  },
+  #[error(
+    "No schedule satisfies the hard constraints. The search got furthest before failing to place class {class_key:?}"
+  )]
+  NoFeasibleSchedule { class_key: ClassKey },
+  #[error("No schedule was found within {0} search nodes")]
+  NodeLimitReached(u64),
+}
+
+type Cell = (week_calendar::Day, week_calendar::Timeslot);
+
+/// Depth-first branch and bound over the class hours. Every partial schedule satisfies the hard
+/// constraints, and since the optimized cost can only grow as hours are added, a branch is cut
+/// as soon as it reaches the cost of the best schedule found.
+struct Search<'a> {
+  constraints: &'a OptimizationConstraints,
+  weights: &'a HeuristicWeights,
+  /// One entry per class hour left to place. Hours of the same class are contiguous.
+  hours: Vec<ClassKey>,
+  domains: SecondaryMap<ClassKey, Vec<Cell>>,
+  /// Lower bound of the cost of placing the hours from each depth on.
+  remaining_cost_bounds: Vec<u64>,
+  state: ClassCalendar,
+  cost: u64,
+  best: Option<(ClassCalendar, u64)>,
+  nodes: u64,
+  max_nodes: u64,
+  deepest_failure: Option<(usize, ClassKey)>,
+}
+
+impl Search<'_> {
+  fn professor_busy(&self, class_key: ClassKey, (day, timeslot): Cell) -> bool {
+    let professor_key = self.constraints.classes[class_key].professor_key;
+    self.state.iter_class_keys().any(|other| {
+      self.constraints.classes[other].professor_key == professor_key
+        && self.state.get_count(day, timeslot, other) > 0
+    })
+  }
+
+  fn cell_cost(&self, (day, timeslot): Cell) -> u64 {
+    self.weights.simultaneous_classes * same_timeslot_classes_count_at(&self.state, day, timeslot)
+      + self.weights.semester_simultaneous_classes
+        * same_timeslot_classes_count_per_semester_at(&self.state, self.constraints, day, timeslot)
+  }
+
+  /// Cost added by placing the class in the cell, or `None` if it breaks a hard constraint.
+  fn placement_cost(&mut self, class_key: ClassKey, cell: Cell) -> Option<u64> {
+    if self.professor_busy(class_key, cell) {
+      return None;
+    }
+    let (day, timeslot) = cell;
+    let before = self.cell_cost(cell);
+    self.state.add_one_class(day, timeslot, class_key).ok()?;
+    let fits =
+      count_timeslot_classroom_assignment_collisions(&self.state, self.constraints, day, timeslot)
+        == 0;
+    let after = self.cell_cost(cell);
+    self
+      .state
+      .remove_one_class(day, timeslot, class_key)
+      .unwrap();
+    let professor = &self.constraints.professors[self.constraints.classes[class_key].professor_key];
+    let if_needed = professor.availability.get(day, timeslot) == &Availability::AvailableIfNeeded;
+    fits.then(|| after - before + self.weights.professor_available_if_needed * if_needed as u64)
+  }
+
+  fn search(&mut self, depth: usize, first_cell: usize) {
+    if self.nodes >= self.max_nodes {
+      return;
+    }
+    self.nodes += 1;
+    if self.best.as_ref().is_some_and(|(_calendar, best_cost)| {
+      self.cost + self.remaining_cost_bounds[depth] >= *best_cost
+    }) {
+      return;
+    }
+    let Some(&class_key) = self.hours.get(depth) else {
+      self.best = Some((self.state.clone(), self.cost));
+      return;
+    };
+
+    // Cheapest placements first, so good schedules are found early and prune more.
+    let candidates = (first_cell..self.domains[class_key].len())
+      .filter_map(|i| {
+        let cell = self.domains[class_key][i];
+        self.placement_cost(class_key, cell).map(|cost| (cost, i))
+      })
+      .sorted()
+      .collect_vec();
+    if candidates.is_empty() && self.deepest_failure.is_none_or(|(d, _)| depth > d) {
+      self.deepest_failure = Some((depth, class_key));
+    }
+
+    for (cost, i) in candidates {
+      let (day, timeslot) = self.domains[class_key][i];
+      self.state.add_one_class(day, timeslot, class_key).unwrap();
+      self.cost += cost;
+      // Hours of the same class are interchangeable, so they are placed in increasing cells.
+      let next_first_cell = if self.hours.get(depth + 1) == Some(&class_key) {
+        i + 1
+      } else {
+        0
+      };
+      self.search(depth + 1, next_first_cell);
+      self.cost -= cost;
+      self
+        .state
+        .remove_one_class(day, timeslot, class_key)
+        .unwrap();
+    }
+  }
+}
+
+/// Finds a schedule that satisfies every hard constraint and minimizes [`ExactSolution::cost`], or
+/// explains why there is none. The search is exponential, so it's meant for small instances
+/// like a single semester or group.
+fn solve(
+  constraints: &OptimizationConstraints,
+  options: ExactSolverOptions,
+) -> Result<ExactSolution, ExactSolverError> {
+  let is_free = |class_key: ClassKey| {
+    options
+      .classes
+      .as_ref()
+      .is_none_or(|classes| classes.contains(&class_key))
+  };
+
+  let mut fixed_state = options.initial_state;
+  let free_entries = fixed_state
+    .class_entries()
+    .iter()
+    .filter(|entry| !entry.locked && is_free(entry.class_key))
+    .map(|entry| (entry.day, entry.timeslot, entry.class_key))
+    .collect_vec();
+  for (day, timeslot, class_key) in free_entries.iter() {
+    fixed_state
+      .remove_one_class(*day, *timeslot, *class_key)
+      .unwrap();
+  }
+  let hard_constraint_violations = HardConstraintViolations::count(&fixed_state, constraints);
+  if !hard_constraint_violations.is_feasible() {
+    return Err(ExactSolverError::FixedClassesConflict(
+      hard_constraint_violations,
+    ));
+  }
+
+  let class_hours = free_entries
+    .iter()
+    .map(|(_day, _timeslot, class_key)| *class_key)
+    .counts();
+  let week_layout = fixed_state.week_layout().clone();
+  let mut search = Search {
+    constraints,
+    weights: &options.heuristic_weights,
+    hours: Vec::new(),
+    domains: SecondaryMap::new(),
+    remaining_cost_bounds: Vec::new(),
+    state: fixed_state,
+    cost: 0,
+    best: None,
+    nodes: 0,
+    max_nodes: options.max_nodes,
+    deepest_failure: None,
+  };
+
+  for &class_key in class_hours.keys() {
+    let professor = &constraints.professors[constraints.classes[class_key].professor_key];
+    let domain = week_layout
+      .days()
+      .flat_map(|day| week_layout.timeslots().map(move |timeslot| (day, timeslot)))
+      .filter(|(day, timeslot)| {
+        professor.availability.get(*day, *timeslot) != &Availability::NotAvailable
+      })
+      .filter(|cell| search.placement_cost(class_key, *cell).is_some())
+      .collect_vec();
+    if domain.len() < class_hours[&class_key] {
+      return Err(ExactSolverError::NotEnoughTimeslots {
+        class_key,
+        hours: class_hours[&class_key],
+        available_timeslots: domain.len(),
+      });
+    }
+    search.domains.insert(class_key, domain);
+  }
+
+  let professor_classes = class_hours
+    .keys()
+    .into_group_map_by(|class_key| constraints.classes[**class_key].professor_key);
+  for (professor_key, class_keys) in professor_classes {
+    let hours = class_keys
+      .iter()
+      .map(|class_key| class_hours[*class_key])
+      .sum();
+    let available_timeslots = class_keys
+      .iter()
+      .flat_map(|class_key| search.domains[**class_key].iter())
+      .unique()
+      .count();
+    if hours > available_timeslots {
+      return Err(ExactSolverError::ProfessorOverbooked {
+        professor_key,
+        hours,
+        available_timeslots,
+      });
+    }
+  }
+
+  // Most constrained classes first, so dead ends show up near the root.
+  search.hours = class_hours
+    .iter()
+    .sorted_by_key(|(class_key, hours)| (search.domains[**class_key].len() - **hours, **class_key))
+    .flat_map(|(class_key, hours)| std::iter::repeat_n(*class_key, *hours))
+    .collect();
+  // Hours that don't fit in the cells where the professor is fully available cost at least
+  // `professor_available_if_needed` each.
+  search.remaining_cost_bounds = (0..=search.hours.len())
+    .map(|depth| {
+      search.hours[depth..]
+        .iter()
+        .counts()
+        .into_iter()
+        .map(|(class_key, hours)| {
+          let professor = &constraints.professors[constraints.classes[*class_key].professor_key];
+          let available_cells = search.domains[*class_key]
+            .iter()
+            .filter(|(day, timeslot)| {
+              professor.availability.get(*day, *timeslot) == &Availability::Available
+            })
+            .count();
+          hours.saturating_sub(available_cells) as u64
+            * options.heuristic_weights.professor_available_if_needed
+        })
+        .sum()
+    })
+    .collect();
+  search.search(0, 0);
+
+  let optimal = search.nodes < search.max_nodes;
+  match (search.best, search.deepest_failure) {
+    (Some((calendar, cost)), _) => Ok(ExactSolution {
+      cost_breakdown: options
+        .heuristic_weights
+        .cost_breakdown(&calendar, constraints),
+      calendar,
+      cost,
+      optimal,
+    }),
+    (None, Some((_depth, class_key))) if optimal => {
+      Err(ExactSolverError::NoFeasibleSchedule { class_key })
+    }
+    (None, _) => Err(ExactSolverError::NodeLimitReached(search.max_nodes)),
+  }
+}
+
+#[derive(Default, Debug)]
+pub struct ExactSolver;
+
+impl ExactSolver {
+  /// See [`ExactSolution`] for what the found schedule is optimal for.
+  pub fn solve(
+    &self,
+    constraints: &OptimizationConstraints,
+    options: ExactSolverOptions,
+  ) -> Result<ExactSolution, ExactSolverError> {
+    solve(constraints, options)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::SchoolSchedule;
+
+  use super::*;
+
+  #[test]
+  fn test_exact_solver() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type_key = schedule.add_new_classroom_type();
+    schedule.add_new_classroom(classroom_type_key);
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..3 {
+      let class_key = schedule.add_new_class(professor_key);
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_allowed_classroom_types([classroom_type_key].into());
+      class_entry.set_hours(2);
+    }
+    let constraints = schedule.get_simulation_constraints();
+
+    let solution = ExactSolver
+      .solve(
+        constraints,
+        ExactSolverOptions {
+          initial_state: schedule.class_calendar().clone(),
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    assert!(solution.optimal);
+    assert!(HardConstraintViolations::count(&solution.calendar, constraints).is_feasible());
+    assert_eq!(solution.calendar.class_entries().len(), 6);
+    // Every professor starts only available if needed, and no two classes need to share a cell.
+    assert_eq!(
+      solution.cost,
+      6 * HeuristicWeights::default().professor_available_if_needed
+    );
+    assert!(solution
+      .cost_breakdown
+      .iter()
+      .any(|c| c.name == "professor_available_if_needed" && c.violations == 6));
+  }
+
+  #[test]
+  fn test_exact_solver_infeasible() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type_key = schedule.add_new_classroom_type();
+    schedule.add_new_classroom(classroom_type_key);
+    let professor_key = schedule.add_new_professor();
+    let timeslot_count =
+      schedule.week_layout().day_count() * schedule.week_layout().timeslot_count();
+    for _ in 0..2 {
+      let class_key = schedule.add_new_class(professor_key);
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_allowed_classroom_types([classroom_type_key].into());
+      class_entry.set_hours((timeslot_count / 2 + 1) as u8);
+    }
+
+    let result = ExactSolver.solve(
+      schedule.get_simulation_constraints(),
+      ExactSolverOptions {
+        initial_state: schedule.class_calendar().clone(),
+        ..Default::default()
+      },
+    );
+    assert!(matches!(
+      result,
+      Err(ExactSolverError::ProfessorOverbooked {
+        professor_key: p,
+        ..
+      }) if p == professor_key
+    ));
+  }
+}
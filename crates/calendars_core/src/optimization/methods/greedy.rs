@@ -0,0 +1,127 @@
+use itertools::Itertools;
+
+use crate::{
+  classroom_assignment::count_timeslot_classroom_assignment_collisions,
+  optimization::heuristics::{
+    same_timeslot_classes_count_at, same_timeslot_classes_count_per_semester_at,
+  },
+  week_calendar, Availability, ClassCalendar, ClassKey, OptimizationConstraints,
+};
+
+/// How bad it is to put one more hour of the class in the cell. Compared lexicographically:
+/// hard constraint violations first, then the soft ones.
+fn placement_key(
+  state: &mut ClassCalendar,
+  constraints: &OptimizationConstraints,
+  class_key: ClassKey,
+  day: week_calendar::Day,
+  timeslot: week_calendar::Timeslot,
+) -> (u64, bool, u64, u64) {
+  let class = &constraints.classes[class_key];
+  let availability = constraints.professors[class.professor_key]
+    .availability
+    .get(day, timeslot);
+  let professor_busy = state.iter_class_keys().any(|other| {
+    constraints.classes[other].professor_key == class.professor_key
+      && state.get_count(day, timeslot, other) > 0
+  });
+  state.add_one_class(day, timeslot, class_key).unwrap();
+  let classroom_collisions =
+    count_timeslot_classroom_assignment_collisions(state, constraints, day, timeslot);
+  let semester_classes =
+    same_timeslot_classes_count_per_semester_at(state, constraints, day, timeslot);
+  let classes = same_timeslot_classes_count_at(state, day, timeslot);
+  state.remove_one_class(day, timeslot, class_key).unwrap();
+  (
+    (availability == &Availability::NotAvailable) as u64
+      + professor_busy as u64
+      + classroom_collisions,
+    availability == &Availability::AvailableIfNeeded,
+    semester_classes,
+    classes,
+  )
+}
+
+/// Builds a starting schedule by placing the unlocked hours of `state` one by one in the least
+/// conflicting timeslot, most constrained classes first. Locked entries are kept in place.
+pub fn greedy_class_calendar(
+  constraints: &OptimizationConstraints,
+  state: &ClassCalendar,
+) -> ClassCalendar {
+  let mut state = state.clone();
+  let unlocked_entries = state
+    .class_entries()
+    .iter()
+    .filter(|entry| !entry.locked)
+    .map(|entry| (entry.day, entry.timeslot, entry.class_key))
+    .collect_vec();
+  for (day, timeslot, class_key) in unlocked_entries.iter() {
+    state.remove_one_class(*day, *timeslot, *class_key).unwrap();
+  }
+
+  let week_layout = state.week_layout().clone();
+  let cells = week_layout
+    .days()
+    .flat_map(|day| week_layout.timeslots().map(move |timeslot| (day, timeslot)))
+    .collect_vec();
+  let class_hours = unlocked_entries
+    .iter()
+    .map(|(_day, _timeslot, class_key)| *class_key)
+    .counts();
+  // Classes with the least spare timeslots go first, while there's still room for them.
+  let classes = class_hours.into_iter().sorted_by_key(|(class_key, hours)| {
+    let availability =
+      &constraints.professors[constraints.classes[*class_key].professor_key].availability;
+    let available_cells = cells
+      .iter()
+      .filter(|(day, timeslot)| availability.get(*day, *timeslot) != &Availability::NotAvailable)
+      .count();
+    (available_cells as i64 - *hours as i64, *class_key)
+  });
+
+  for (class_key, hours) in classes {
+    for _ in 0..hours {
+      let (day, timeslot) = *cells
+        .iter()
+        .min_by_key(|(day, timeslot)| {
+          placement_key(&mut state, constraints, class_key, *day, *timeslot)
+        })
+        .unwrap();
+      state.add_one_class(day, timeslot, class_key).unwrap();
+    }
+  }
+  state
+}
+
+#[cfg(test)]
+mod test {
+  use crate::{HardConstraintViolations, SchoolSchedule};
+
+  use super::*;
+
+  #[test]
+  fn test_greedy_class_calendar() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type_key = schedule.add_new_classroom_type();
+    schedule.add_new_classroom(classroom_type_key);
+    let professor_key = schedule.add_new_professor();
+    let mut class_keys = Vec::new();
+    for hours in [1, 2, 2] {
+      let class_key = schedule.add_new_class(professor_key);
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_allowed_classroom_types([classroom_type_key].into());
+      class_entry.set_hours(hours);
+      class_keys.push(class_key);
+    }
+    let day = week_calendar::Day::from_usize(0).unwrap();
+    let timeslot = week_calendar::Timeslot::from_usize(0).unwrap();
+    schedule.set_locked(day, timeslot, class_keys[0], true);
+    let constraints = schedule.get_simulation_constraints();
+    assert!(!schedule.hard_constraint_violations().is_feasible());
+
+    let state = greedy_class_calendar(constraints, schedule.class_calendar());
+    assert!(HardConstraintViolations::count(&state, constraints).is_feasible());
+    assert_eq!(state.class_entries().len(), 5);
+    assert!(state.is_locked(day, timeslot, class_keys[0]));
+  }
+}
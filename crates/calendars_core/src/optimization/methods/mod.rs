@@ -1 +1,4 @@
+pub mod exact_solver;
+pub mod greedy;
 pub mod simulated_annealing;
+pub mod tabu_search;
@@ -1,28 +1,36 @@
 use std::{
   collections::BTreeMap,
   sync::{
-    atomic::{AtomicU64, Ordering},
+    atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
     Arc, Barrier, RwLock,
   },
   thread::JoinHandle,
 };
 
-use indicatif::{HumanCount, HumanDuration, ProgressStyle};
 use itertools::Itertools;
+use rand::distributions::WeightedIndex;
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
-use strum::IntoEnumIterator;
+use strum::VariantArray;
 
 use crate::{
-  classroom_assignment::{assign_classrooms, count_classroom_assignment_collisions},
-  optimization::{class_calendar::ClassEntryDelta, heuristics, stats_tracker::StatsTracker},
-  school_schedule::ClassroomAssignmentKey,
-  ClassCalendar, ClassCalendarOptimizer, Classroom, OptimizationConstraints,
+  classroom_assignment::assign_classrooms,
+  optimization::{
+    class_calendar::{ClassEntryDelta, MoveOneClassRandomError},
+    hard_constraints::HardConstraintViolations,
+    heuristics::{self, Heuristic, Violation},
+    progress::{CombinedProgress, Progress, ProgressObserver},
+    stats_tracker::StatsTracker,
+  },
+  school_schedule::{classroom_assignments_serde, ClassroomAssignmentKey},
+  ClassCalendar, ClassCalendarOptimizer, ClassroomKey, OptimizationConstraints,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SimulationOutput {
-  pub simulation_options: SimulationOptions,
+  /// Configuration of the run, with the seed it used.
+  pub simulation_options: AnnealingConfig,
   pub final_calendar: ClassCalendar,
   pub final_cost: f64,
   pub start_time: std::time::SystemTime,
@@ -36,22 +44,47 @@ pub struct SimulationOutput {
   /// Needed if stop condition is not number of steps
   pub total_steps: usize,
 
-  #[serde(skip)]
-  pub classroom_assignments: BTreeMap<ClassroomAssignmentKey, Classroom>,
+  #[serde(default, with = "classroom_assignments_serde")]
+  pub classroom_assignments: BTreeMap<ClassroomAssignmentKey, ClassroomKey>,
+
+  #[serde(default)]
+  pub hard_constraint_violations: HardConstraintViolations,
+
+  /// Cost of `final_calendar` per heuristic.
+  #[serde(default)]
+  pub cost_breakdown: Vec<HeuristicCost>,
+
+  /// Seed the run used. Running again with it and the same options gives the same calendar.
+  #[serde(default)]
+  pub seed: u64,
+
+  /// Lowest cost calendar visited during the run, among the ones with the fewest hard constraint
+  /// violations. Can be better than `final_calendar`, which is just where the run ended.
+  #[serde(default)]
+  pub best_calendar: ClassCalendar,
+  #[serde(default)]
+  pub best_cost: f64,
+  #[serde(default)]
+  pub best_hard_constraint_violations: HardConstraintViolations,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct SimulationRunReport {
-  pub num_steps: u64,
-  pub stats: BTreeMap<String, Vec<serde_json::Value>>,
-  pub start_time: std::time::SystemTime,
+/// Contribution of a single heuristic to the cost of a schedule.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HeuristicCost {
+  pub name: String,
+  pub violations: u64,
+  pub weight: u64,
+  /// `violations * weight`, in the same scale as the total cost.
+  pub cost: f64,
 }
 
-fn simulated_annealing<R: Rng>(
+fn simulated_annealing(
   constraints: &OptimizationConstraints,
-  options: SimulationOptions,
-  mut rng: R,
+  options: AnnealingConfig,
+  custom_heuristics: &[WeightedHeuristic],
 ) -> SimulationOutput {
+  let seed = options.seed.unwrap_or_else(|| thread_rng().gen());
+  let mut rng = ChaCha8Rng::seed_from_u64(seed);
   let start_time = std::time::SystemTime::now();
   let start_instant = std::time::Instant::now();
 
@@ -62,109 +95,140 @@ fn simulated_annealing<R: Rng>(
 
   // let mut state = random_init(constraints, &mut rng);
   let mut state = options.initial_state.clone();
-  let mut par_eval = ParEvaluator::new(state.clone(), constraints.clone());
-  let mut state_cost = cost(&mut par_eval, &state, constraints);
-
-  let mut progress_bar: Option<indicatif::ProgressBar> = {
-    match (options.progress, &options.stop_condition) {
-      (ProgressOption::ProgressBar(pb), StopCondition::Steps(total_steps)) => {
-        pb.set_length(*total_steps as u64);
-        pb.set_position(0);
-        Some(pb)
-      }
-      (ProgressOption::MultiProgress(mp), StopCondition::Steps(total_steps)) => {
-        let progress_bar_style = ProgressStyle::with_template(
-              "{spinner:.green} [{elapsed_precise}] [{bar:.cyan/blue}] {human_pos}/{human_len} ({percent} %) ({eta}) ({per_sec})",
-            )
-            .unwrap()
-            .progress_chars("#>-");
-        let pb = indicatif::ProgressBar::new(*total_steps as u64).with_style(progress_bar_style);
-        let pb = mp.add(pb);
-        Some(pb)
-      }
-      (ProgressOption::MultiProgress(mp), StopCondition::Time(total_duration)) => {
-        let human_total_time = HumanDuration(*total_duration);
-        let pb_style = ProgressStyle::with_template(
-            format!(
-              "{{spinner:.green}} [{{bar:.cyan/blue}}] ({{percent}} %) [{{elapsed}}/{}] ({{msg}} steps)",
-              human_total_time
-            )
-            .as_str(),
-          )
-          .unwrap()
-          .progress_chars("#>-");
-        let pb = indicatif::ProgressBar::new(total_duration.as_secs())
-          .with_style(pb_style)
-          .with_message("0");
-        let pb = mp.add(pb);
-        Some(pb)
-      }
-      (ProgressOption::ProgressBar(pb), StopCondition::Time(total_duration)) => {
-        pb.set_length(total_duration.as_secs());
-        pb.set_position(0);
-        Some(pb)
-      }
-      (ProgressOption::None, _) => None,
-    }
+  let all_heuristics = options
+    .heuristic_weights
+    .built_in_heuristics()
+    .into_iter()
+    .chain(custom_heuristics.iter().cloned())
+    .collect_vec();
+  // Zero-weight heuristics add nothing to the cost, so they're only evaluated for the breakdown.
+  let heuristics = all_heuristics
+    .iter()
+    .filter(|weighted_heuristic| weighted_heuristic.weight > 0)
+    .cloned()
+    .collect_vec();
+  let cost_labels = heuristics
+    .iter()
+    .map(|weighted_heuristic| format!("cost_{}", weighted_heuristic.heuristic.name()))
+    .collect_vec();
+  let mut par_eval = ParEvaluator::new(state.clone(), constraints.clone(), heuristics.clone());
+  let mut state_cost = cost(&par_eval, &state, constraints, &heuristics);
+  let move_distribution = options.move_probabilities.distribution();
+  let mut state_hard_constraint_violations = HardConstraintViolations::count(&state, constraints);
+  let mut best_state = state.clone();
+  let mut best_cost = state_cost;
+  let mut best_hard_constraint_violations = state_hard_constraint_violations;
+  let t_amplitude = match options.initial_temperature {
+    InitialTemperature::Fixed(temperature) => temperature,
+    InitialTemperature::Auto {
+      acceptance_probability,
+    } => calibrate_temperature(
+      &mut par_eval,
+      &mut state,
+      &state_hard_constraint_violations,
+      &move_distribution,
+      acceptance_probability,
+      &mut rng,
+    ),
   };
 
+  let max_steps = stop_condition.max_steps();
+
   let mut step_idx = 0;
-  while match stop_condition {
-    StopCondition::Steps(total_steps) => step_idx < *total_steps,
-    StopCondition::Time(total_time) => start_instant.elapsed().lt(total_time),
-  } {
+  let mut steps_since_improvement = 0;
+  loop {
+    let run_progress = RunProgress {
+      steps: step_idx,
+      elapsed: start_instant.elapsed(),
+      best_cost,
+      best_hard_constraint_violations: best_hard_constraint_violations.total(),
+      steps_since_improvement,
+    };
+    if stop_condition.is_met(&run_progress) || !options.control.wait_while_paused() {
+      break;
+    }
     stats.log_stat("curr_cost", state_cost).unwrap();
+    for (label, cost) in cost_labels.iter().zip(par_eval.costs()) {
+      stats.log_stat(label, cost).unwrap();
+    }
 
-    let x = match stop_condition {
-      StopCondition::Steps(total_steps) => ((step_idx + 1) as f64) / (*total_steps as f64),
-      StopCondition::Time(total_time) => {
-        (start_instant.elapsed().as_nanos() / total_time.as_nanos()).max(1) as f64
-      }
-    };
+    let fraction_done = stop_condition.fraction_done(&run_progress);
+    let x = fraction_done.unwrap_or(0.0);
     stats.log_stat("x", x).unwrap();
 
-    let t_amplitude = 3.0;
     let t = temperature(x, temperature_function, t_amplitude);
     stats.log_stat("temperature", t).unwrap();
 
     let old_cost = state_cost;
-    let delta = state.move_one_class_random(&mut rng).unwrap();
-    par_eval.apply_change(&delta);
+    let deltas = match random_move(&mut state, &move_distribution, &mut rng) {
+      Ok(deltas) => deltas,
+      // Every class is locked (or there are none), nothing left to optimize.
+      Err(MoveOneClassRandomError::NoClassesToMove) => break,
+      Err(e) => panic!("{e}"),
+    };
+    let new_hard_constraint_violations =
+      par_eval.propose_move(&deltas, &state_hard_constraint_violations);
 
-    let new_cost = cost(&mut par_eval, &state, constraints);
+    let new_cost = cost(&par_eval, &state, constraints, &heuristics);
     stats.log_stat("new_cost", new_cost).unwrap();
 
-    let ap = acceptance_probability(old_cost, new_cost, t);
+    debug_assert_eq!(
+      new_hard_constraint_violations,
+      HardConstraintViolations::count(&state, constraints)
+    );
+    stats
+      .log_stat(
+        "hard_constraint_violations",
+        new_hard_constraint_violations.total(),
+      )
+      .unwrap();
+
+    let is_feasible_move = new_hard_constraint_violations.within(&state_hard_constraint_violations);
+    let ap = if is_feasible_move {
+      acceptance_probability(old_cost, new_cost, t)
+    } else {
+      0.0
+    };
     stats.log_stat("acceptance_probability", ap).unwrap();
 
-    if ap >= rng.gen_range(0.0..=1.0) {
+    steps_since_improvement += 1;
+    if is_feasible_move && ap >= rng.gen_range(0.0..=1.0) {
       stats.log_stat("accepted", true).unwrap();
       // keep change
+      par_eval.accept_move();
       state_cost = new_cost;
+      state_hard_constraint_violations = new_hard_constraint_violations;
+      if (state_hard_constraint_violations.total(), state_cost)
+        < (best_hard_constraint_violations.total(), best_cost)
+      {
+        best_state.clone_from(&state);
+        best_cost = state_cost;
+        best_hard_constraint_violations = state_hard_constraint_violations;
+        steps_since_improvement = 0;
+      }
     } else {
       stats.log_stat("accepted", false).unwrap();
-      revert_change(&mut state, &delta);
-      par_eval.apply_change(&swap_delta(delta));
+      for delta in deltas.iter().rev() {
+        revert_change(&mut state, delta);
+      }
+      par_eval.reject_move();
       state_cost = old_cost;
     }
 
     stats.inc_step();
-    if step_idx % options.advanced_options.progress_bar_update_interval == 0 {
-      match (progress_bar.as_mut(), stop_condition) {
-        (Some(pb), StopCondition::Steps(_)) => {
-          pb.set_position(step_idx as u64);
-        }
-        (Some(pb), StopCondition::Time(_)) => {
-          let human_steps = HumanCount(step_idx as u64);
-          pb.set_position(start_instant.elapsed().as_secs());
-          pb.set_message(human_steps.to_string());
-        }
-        _ => (),
-      };
-    }
-    if let Some(live_update) = options.advanced_options.live_update.as_ref() {
-      if step_idx % live_update.live_update_interval == 0 {
-        if let Ok(()) = live_update.channel.send(state.clone()) {}
+    if let Some(observer) = options.progress_observer.as_ref() {
+      if step_idx % options.progress_interval == 0 {
+        observer.on_progress(&Progress {
+          step: step_idx,
+          total_steps: max_steps,
+          fraction_done,
+          elapsed: start_instant.elapsed(),
+          cost: state_cost,
+          temperature: Some(t),
+          best_cost,
+          best_hard_constraint_violations: best_hard_constraint_violations.total(),
+          best_calendar: &best_state,
+        });
       }
     }
     step_idx += 1;
@@ -172,22 +236,35 @@ fn simulated_annealing<R: Rng>(
 
   let end_time = std::time::SystemTime::now();
   let duration = start_instant.elapsed();
-
-  for (i, evaluator) in EVALUATORS.iter().enumerate() {
-    let r = evaluator(&state, constraints);
-    let r = r as f64;
-    let r = r / (EVALUATORS_FACTOR as f64);
-    println!("Evaluator {i}: {r}")
+  if let Some(observer) = options.progress_observer.as_ref() {
+    observer.on_finish(&Progress {
+      step: step_idx,
+      total_steps: max_steps,
+      fraction_done: max_steps.map(|total_steps| step_idx as f64 / total_steps as f64),
+      elapsed: duration,
+      cost: state_cost,
+      temperature: None,
+      best_cost,
+      best_hard_constraint_violations: best_hard_constraint_violations.total(),
+      best_calendar: &best_state,
+    });
   }
-  println!("Total: {}", state_cost);
+
+  let cost_breakdown = cost_breakdown(&state, constraints, &all_heuristics);
   let classroom_assignments = assign_classrooms(&state, constraints);
+  let hard_constraint_violations = HardConstraintViolations::count(&state, constraints);
   SimulationOutput {
-    simulation_options: SimulationOptions {
+    simulation_options: AnnealingConfig {
       initial_state: options.initial_state,
-      progress: ProgressOption::None,
-      temperature_function: options.temperature_function,
-      advanced_options: Default::default(),
       stop_condition: options.stop_condition,
+      temperature_function: options.temperature_function,
+      initial_temperature: options.initial_temperature,
+      heuristic_weights: options.heuristic_weights,
+      move_probabilities: options.move_probabilities,
+      seed: Some(seed),
+      progress_observer: None,
+      progress_interval: options.progress_interval,
+      control: Default::default(),
     },
     total_steps: step_idx,
     final_calendar: state,
@@ -197,6 +274,12 @@ fn simulated_annealing<R: Rng>(
     duration,
     stats: stats.into_stats(),
     classroom_assignments,
+    hard_constraint_violations,
+    cost_breakdown,
+    seed,
+    best_calendar: best_state,
+    best_cost,
+    best_hard_constraint_violations,
   }
 }
 
@@ -211,21 +294,96 @@ fn acceptance_probability(old_cost: f64, new_cost: f64, temperature: f64) -> f64
 fn temperature(x: f64, temperature_function_variant: &TemperatureFunction, amplitude: f64) -> f64 {
   (match temperature_function_variant {
     TemperatureFunction::Linear => 1.0 - x,
+    TemperatureFunction::Exponential => 0.001_f64.powf(x),
+    TemperatureFunction::Logarithmic => 1.0 / (1.0 + (1.0 + 1000.0 * x).ln()),
+    TemperatureFunction::Reheating { cycles } => {
+      let cycles = (*cycles).max(1) as f64;
+      let cycle = (x * cycles).floor().min(cycles - 1.0);
+      (1.0 - cycle / cycles) * (1.0 - (x * cycles - cycle))
+    }
   })
   .clamp(0.0, 1.0)
     * amplitude
 }
 
-fn swap_delta(delta: ClassEntryDelta) -> ClassEntryDelta {
-  ClassEntryDelta {
-    class_key: delta.class_key,
-    src_day: delta.dst_day,
-    src_timeslot: delta.dst_timeslot,
-    dst_day: delta.src_day,
-    dst_timeslot: delta.src_timeslot,
+/// Number of random moves sampled to calibrate the initial temperature.
+const TEMPERATURE_CALIBRATION_SAMPLES: usize = 200;
+
+/// Samples random moves from `state` and returns the temperature at which the average move that
+/// increases the cost is accepted with `acceptance_probability`. The state is left unchanged.
+fn calibrate_temperature<R: Rng>(
+  par_eval: &mut ParEvaluator,
+  state: &mut ClassCalendar,
+  hard_constraint_violations: &HardConstraintViolations,
+  move_distribution: &Option<WeightedIndex<f64>>,
+  acceptance_probability: f64,
+  rng: &mut R,
+) -> f64 {
+  let cost = par_eval.cost();
+  let mut cost_increases = Vec::new();
+  for _ in 0..TEMPERATURE_CALIBRATION_SAMPLES {
+    let Ok(deltas) = random_move(state, move_distribution, rng) else {
+      break;
+    };
+    let new_hard_constraint_violations = par_eval.propose_move(&deltas, hard_constraint_violations);
+    let new_cost = par_eval.cost();
+    // Moves that add hard constraint violations are never accepted, their cost doesn't matter.
+    if new_cost > cost && new_hard_constraint_violations.within(hard_constraint_violations) {
+      cost_increases.push(new_cost - cost);
+    }
+    for delta in deltas.iter().rev() {
+      revert_change(state, delta);
+    }
+    par_eval.reject_move();
+  }
+  if cost_increases.is_empty() {
+    return InitialTemperature::DEFAULT_TEMPERATURE;
+  }
+  let mean_cost_increase = cost_increases.iter().sum::<f64>() / cost_increases.len() as f64;
+  -mean_cost_increase
+    / acceptance_probability
+      .clamp(f64::EPSILON, 1.0 - f64::EPSILON)
+      .ln()
+}
+
+/// Makes a move of the kind drawn from `move_distribution` and returns its changes. Falls back
+/// to moving a single hour when the drawn kind has no valid move (e.g. the session doesn't fit
+/// in the destination).
+fn random_move<R: Rng>(
+  state: &mut ClassCalendar,
+  move_distribution: &Option<WeightedIndex<f64>>,
+  rng: &mut R,
+) -> Result<Vec<ClassEntryDelta>, MoveOneClassRandomError> {
+  let deltas = match move_distribution
+    .as_ref()
+    .map(|distribution| MoveKind::VARIANTS[distribution.sample(rng)])
+  {
+    Some(MoveKind::SwapSessions) => state.swap_sessions_random_deltas(rng),
+    Some(MoveKind::Session) => state.move_session_random_deltas(rng),
+    Some(MoveKind::DayShift) => state.shift_session_day_random_deltas(rng),
+    Some(MoveKind::SingleHour) | None => None,
+  };
+  match deltas {
+    Some(deltas) => {
+      for delta in deltas.iter() {
+        apply_change(state, delta);
+      }
+      Ok(deltas)
+    }
+    None => Ok(vec![state.move_one_class_random(rng)?]),
   }
 }
 
+fn apply_change(state: &mut ClassCalendar, delta: &ClassEntryDelta) {
+  state.move_one_class(
+    delta.src_day,
+    delta.src_timeslot,
+    delta.dst_day,
+    delta.dst_timeslot,
+    delta.class_key,
+  );
+}
+
 fn revert_change(state: &mut ClassCalendar, delta: &ClassEntryDelta) {
   state.move_one_class(
     delta.dst_day,
@@ -236,34 +394,77 @@ fn revert_change(state: &mut ClassCalendar, delta: &ClassEntryDelta) {
   );
 }
 
-#[rustfmt::skip]
-  const EVALUATORS: [fn(&ClassCalendar, &OptimizationConstraints)->u64; 11] = [
-    |state,  constraints| 10000 * (count_classroom_assignment_collisions(state, constraints) as u64),
-    |state,  constraints|  9000 * heuristics::same_timeslot_classes_count_per_professor(state, constraints),
-    |state,  constraints|  5000 * heuristics::same_timeslot_classes_count_per_semester(state, constraints),
-    |state,  constraints|  4500 * heuristics::count_labs_on_different_days(state, constraints),
-    |state,  constraints|  3000 * heuristics::count_not_available(state, constraints),
-    |state, _constraints|  2500 * heuristics::count_incontinuous_classes(state),
-    |state, _constraints|  1500 * heuristics::count_outside_session_length(state, 2, 4),
-    |state,  constraints|  1300 * heuristics::count_holes_per_semester(state, constraints),
-    |state,  constraints|  1250 * heuristics::count_available_if_needed(state, constraints),
-    |state, _constraints|  1000 * heuristics::count_inconsistent_class_timeslots(state),
-    |state, _constraints|   100 * heuristics::same_timeslot_classes_count(state),
-  ];
-const EVALUATORS_FACTOR: u64 = 1000;
+#[derive(Clone)]
+pub(crate) struct WeightedHeuristic {
+  pub(crate) heuristic: Arc<dyn Heuristic>,
+  pub(crate) weight: u64,
+}
+
+impl WeightedHeuristic {
+  pub(crate) fn new(heuristic: impl Heuristic + 'static, weight: u64) -> Self {
+    Self {
+      heuristic: Arc::new(heuristic),
+      weight,
+    }
+  }
+
+  pub(crate) fn evaluate(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> u64 {
+    self.weight * self.heuristic.evaluate(state, constraints)
+  }
+
+  pub(crate) fn evaluate_delta(
+    &self,
+    before: &ClassCalendar,
+    after: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+    delta: &ClassEntryDelta,
+  ) -> Option<i64> {
+    self
+      .heuristic
+      .evaluate_delta(before, after, constraints, delta)
+      .map(|cost_delta| self.weight as i64 * cost_delta)
+  }
+}
+
+pub(crate) const EVALUATORS_FACTOR: u64 = 1000;
+
+pub(crate) fn cost_breakdown(
+  state: &ClassCalendar,
+  constraints: &OptimizationConstraints,
+  heuristics: &[WeightedHeuristic],
+) -> Vec<HeuristicCost> {
+  heuristics
+    .iter()
+    .map(|weighted_heuristic| {
+      let violations = weighted_heuristic.heuristic.evaluate(state, constraints);
+      HeuristicCost {
+        name: weighted_heuristic.heuristic.name().to_string(),
+        violations,
+        weight: weighted_heuristic.weight,
+        cost: (violations * weighted_heuristic.weight) as f64 / (EVALUATORS_FACTOR as f64),
+      }
+    })
+    .collect()
+}
 
 fn cost(
-  par_eval: &mut ParEvaluator,
+  par_eval: &ParEvaluator,
   state: &ClassCalendar,
   constraints: &OptimizationConstraints,
+  heuristics: &[WeightedHeuristic],
 ) -> f64 {
-  let r0 = par_eval.eval_cost();
+  let r0 = par_eval.cost();
 
   #[cfg(debug_assertions)]
   {
-    // assert_eq!(state.clone(), par_eval.get_curr_state());
-
-    let r2: u64 = EVALUATORS.iter().map(|f| f(state, constraints)).sum();
+    let r2: u64 = heuristics
+      .iter()
+      .map(|heuristic| heuristic.evaluate(state, constraints))
+      .sum();
     let r2 = r2 as f64;
     let r2 = r2 / (EVALUATORS_FACTOR as f64);
 
@@ -273,126 +474,395 @@ fn cost(
   r0
 }
 
+/// Evaluates every heuristic on its own thread. Only the proposed change is evaluated, and
+/// heuristics that support it compute their difference from the last accepted state. Moves of
+/// several hours are evaluated one hour at a time.
 struct ParEvaluator {
+  /// State after the last evaluated change.
   state: Arc<RwLock<ClassCalendar>>,
+  /// `state` with the proposed change applied.
+  proposed_state: Arc<RwLock<ClassCalendar>>,
+  proposed_change: Arc<RwLock<Option<ClassEntryDelta>>>,
+  constraints: Arc<RwLock<OptimizationConstraints>>,
   start_eval_barrier: Arc<Barrier>,
   finish_eval_barrier: Arc<Barrier>,
-  _evaluator_handles: Vec<JoinHandle<()>>,
-  cost_counter: Arc<AtomicU64>,
+  /// Tells the evaluator threads to exit when they're next started.
+  shutdown: Arc<AtomicBool>,
+  evaluator_handles: Vec<JoinHandle<()>>,
+  /// Weighted cost of each heuristic for `state`.
+  costs: Arc<Vec<AtomicU64>>,
+  /// Weighted cost of each heuristic for `proposed_state`.
+  proposed_costs: Arc<Vec<AtomicU64>>,
+  /// Changes of the move being evaluated, already applied to `state`.
+  move_changes: Vec<ClassEntryDelta>,
+  /// `costs` before the move being evaluated.
+  move_start_costs: Vec<u64>,
 }
 
 impl ParEvaluator {
-  fn new(init_state: ClassCalendar, init_constraints: OptimizationConstraints) -> Self {
-    let cost_counter = Arc::new(AtomicU64::new(0));
-    let state = Arc::new(RwLock::new(init_state));
+  fn new(
+    init_state: ClassCalendar,
+    init_constraints: OptimizationConstraints,
+    heuristics: Vec<WeightedHeuristic>,
+  ) -> Self {
+    let costs = Arc::new(heuristics.iter().map(|_| AtomicU64::new(0)).collect_vec());
+    let proposed_costs = Arc::new(heuristics.iter().map(|_| AtomicU64::new(0)).collect_vec());
+    let state = Arc::new(RwLock::new(init_state.clone()));
+    let proposed_state = Arc::new(RwLock::new(init_state));
+    let proposed_change = Arc::new(RwLock::new(None));
     let constraints = Arc::new(RwLock::new(init_constraints));
-    let start_eval_barrier = Arc::new(Barrier::new(1 + EVALUATORS.len()));
-    let finish_eval_barrier = Arc::new(Barrier::new(1 + EVALUATORS.len()));
+    let start_eval_barrier = Arc::new(Barrier::new(1 + heuristics.len()));
+    let finish_eval_barrier = Arc::new(Barrier::new(1 + heuristics.len()));
+    let shutdown = Arc::new(AtomicBool::new(false));
 
-    let evaluator_handles = (0..EVALUATORS.len())
-      .map(|f_i| {
+    let evaluator_handles = heuristics
+      .into_iter()
+      .enumerate()
+      .map(|(i, heuristic)| {
         let local_state = state.clone();
+        let local_proposed_state = proposed_state.clone();
+        let local_proposed_change = proposed_change.clone();
         let local_constraints = constraints.clone();
         let local_start_eval_barrier = start_eval_barrier.clone();
         let local_finish_eval_barrier = finish_eval_barrier.clone();
-        let local_cost_counter = cost_counter.clone();
-        std::thread::spawn(move || {
-          let f = EVALUATORS[f_i];
-          loop {
-            local_start_eval_barrier.wait();
+        let local_costs = costs.clone();
+        let local_proposed_costs = proposed_costs.clone();
+        let local_shutdown = shutdown.clone();
+        std::thread::spawn(move || loop {
+          local_start_eval_barrier.wait();
+          if local_shutdown.load(Ordering::SeqCst) {
+            break;
+          }
+          {
             let lock_state = local_state.read().unwrap();
+            let lock_proposed_state = local_proposed_state.read().unwrap();
+            let lock_proposed_change = local_proposed_change.read().unwrap();
             let lock_constraints = local_constraints.read().unwrap();
-            local_cost_counter.fetch_add(f(&lock_state, &lock_constraints), Ordering::SeqCst);
-            local_finish_eval_barrier.wait();
+            let proposed_cost = lock_proposed_change
+              .as_ref()
+              .and_then(|delta| {
+                heuristic.evaluate_delta(
+                  &lock_state,
+                  &lock_proposed_state,
+                  &lock_constraints,
+                  delta,
+                )
+              })
+              .map(|cost_delta| {
+                local_costs[i]
+                  .load(Ordering::SeqCst)
+                  .checked_add_signed(cost_delta)
+                  .unwrap()
+              })
+              .unwrap_or_else(|| heuristic.evaluate(&lock_proposed_state, &lock_constraints));
+            local_proposed_costs[i].store(proposed_cost, Ordering::SeqCst);
           }
+          local_finish_eval_barrier.wait();
         })
       })
       .collect_vec();
 
-    Self {
+    let mut par_eval = Self {
+      state,
+      proposed_state,
+      proposed_change,
+      constraints,
       start_eval_barrier,
       finish_eval_barrier,
-      _evaluator_handles: evaluator_handles,
-      cost_counter,
-      state,
-    }
+      shutdown,
+      evaluator_handles,
+      costs,
+      proposed_costs,
+      move_changes: Vec::new(),
+      move_start_costs: Vec::new(),
+    };
+    par_eval.eval_cost();
+    par_eval.accept_change();
+    par_eval
   }
 
-  fn apply_change(&mut self, delta: &ClassEntryDelta) {
-    let mut write_lock_state = self.state.write().unwrap();
-    write_lock_state.move_one_class(
+  fn propose_change(&mut self, delta: &ClassEntryDelta) {
+    self.proposed_state.write().unwrap().move_one_class(
       delta.src_day,
       delta.src_timeslot,
       delta.dst_day,
       delta.dst_timeslot,
       delta.class_key,
     );
+    *self.proposed_change.write().unwrap() = Some(delta.clone());
+  }
+
+  fn accept_change(&mut self) {
+    if let Some(delta) = self.proposed_change.write().unwrap().take() {
+      self.state.write().unwrap().move_one_class(
+        delta.src_day,
+        delta.src_timeslot,
+        delta.dst_day,
+        delta.dst_timeslot,
+        delta.class_key,
+      );
+    }
+    for (cost, proposed_cost) in self.costs.iter().zip(self.proposed_costs.iter()) {
+      cost.store(proposed_cost.load(Ordering::SeqCst), Ordering::SeqCst);
+    }
+  }
+
+  /// Applies every change of the move and evaluates the resulting state, which becomes the
+  /// current one until [`Self::accept_move`] or [`Self::reject_move`] are called. Returns the
+  /// hard constraint violations after the move, given the ones before it.
+  fn propose_move(
+    &mut self,
+    deltas: &[ClassEntryDelta],
+    violations: &HardConstraintViolations,
+  ) -> HardConstraintViolations {
+    self.move_start_costs = self
+      .costs
+      .iter()
+      .map(|cost| cost.load(Ordering::SeqCst))
+      .collect();
+    let mut violations = *violations;
+    for delta in deltas {
+      self.propose_change(delta);
+      self.eval_cost();
+      violations = self.proposed_hard_constraint_violations(&violations);
+      self.accept_change();
+      self.move_changes.push(delta.clone());
+    }
+    violations
+  }
+
+  fn accept_move(&mut self) {
+    self.move_changes.clear();
+  }
+
+  fn reject_move(&mut self) {
+    for delta in self.move_changes.drain(..).rev() {
+      revert_change(&mut self.state.write().unwrap(), &delta);
+      revert_change(&mut self.proposed_state.write().unwrap(), &delta);
+    }
+    for (cost, move_start_cost) in self.costs.iter().zip(self.move_start_costs.iter()) {
+      cost.store(*move_start_cost, Ordering::SeqCst);
+    }
+  }
+
+  /// Cost of each heuristic for the current state.
+  fn costs(&self) -> impl Iterator<Item = f64> + '_ {
+    self
+      .costs
+      .iter()
+      .map(|cost| cost.load(Ordering::SeqCst) as f64 / (EVALUATORS_FACTOR as f64))
+  }
+
+  /// Total cost of the current state.
+  fn cost(&self) -> f64 {
+    let r: u64 = self
+      .costs
+      .iter()
+      .map(|cost| cost.load(Ordering::SeqCst))
+      .sum();
+    r as f64 / (EVALUATORS_FACTOR as f64)
   }
 
-  fn eval_cost(&mut self) -> f64 {
-    self.cost_counter.store(0, Ordering::SeqCst);
+  /// Evaluates the proposed state.
+  fn eval_cost(&mut self) {
     self.start_eval_barrier.wait();
-    // All threads start to eval
-    // All threads add to the cost_counter
+    // All threads evaluate their heuristic
     self.finish_eval_barrier.wait();
-    // Wait for all threads to finish
-    // Return the sum from all threads
-    let r = self.cost_counter.load(Ordering::SeqCst);
-    let r = r as f64;
+  }
 
-    r / (EVALUATORS_FACTOR as f64)
+  /// Hard constraint violations of the proposed state, given the ones of the accepted state.
+  fn proposed_hard_constraint_violations(
+    &self,
+    violations: &HardConstraintViolations,
+  ) -> HardConstraintViolations {
+    let state = self.state.read().unwrap();
+    let proposed_state = self.proposed_state.read().unwrap();
+    let constraints = self.constraints.read().unwrap();
+    match self.proposed_change.read().unwrap().as_ref() {
+      Some(delta) => violations.with_delta(&state, &proposed_state, &constraints, delta),
+      None => *violations,
+    }
   }
+}
 
-  fn get_curr_state(&self) -> ClassCalendar {
-    self.state.read().unwrap().clone()
+impl Drop for ParEvaluator {
+  fn drop(&mut self) {
+    self.shutdown.store(true, Ordering::SeqCst);
+    self.start_eval_barrier.wait();
+    for evaluator_handle in self.evaluator_handles.drain(..) {
+      evaluator_handle.join().unwrap();
+    }
   }
 }
 
 use std::time::Duration;
 
+/// Lets the caller stop or pause a running optimization from another thread. Clones share the
+/// same state. A stopped optimization returns the result it has so far.
 #[derive(Debug, Clone, Default)]
-pub enum ProgressOption {
-  ProgressBar(indicatif::ProgressBar),
-  MultiProgress(indicatif::MultiProgress),
-  #[default]
-  None,
+pub struct RunControl {
+  state: Arc<AtomicU8>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl RunControl {
+  const RUNNING: u8 = 0;
+  const PAUSED: u8 = 1;
+  const STOPPED: u8 = 2;
+
+  pub fn stop(&self) {
+    self.state.store(Self::STOPPED, Ordering::SeqCst);
+  }
+
+  /// Does nothing if the run was stopped.
+  pub fn pause(&self) {
+    let _ = self.state.compare_exchange(
+      Self::RUNNING,
+      Self::PAUSED,
+      Ordering::SeqCst,
+      Ordering::SeqCst,
+    );
+  }
+
+  /// Does nothing if the run was stopped.
+  pub fn resume(&self) {
+    let _ = self.state.compare_exchange(
+      Self::PAUSED,
+      Self::RUNNING,
+      Ordering::SeqCst,
+      Ordering::SeqCst,
+    );
+  }
+
+  pub fn is_paused(&self) -> bool {
+    self.state.load(Ordering::SeqCst) == Self::PAUSED
+  }
+
+  pub fn is_stopped(&self) -> bool {
+    self.state.load(Ordering::SeqCst) == Self::STOPPED
+  }
+
+  /// Blocks while the run is paused. Returns `false` if the run must stop.
+  pub(crate) fn wait_while_paused(&self) -> bool {
+    while self.is_paused() {
+      std::thread::sleep(Duration::from_millis(50));
+    }
+    !self.is_stopped()
+  }
+}
+
+/// How the temperature goes down from the initial temperature as the simulation advances.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub enum TemperatureFunction {
+  #[default]
   Linear,
+  /// Decays geometrically, down to a thousandth of the initial temperature.
+  Exponential,
+  /// Drops fast at first and then very slowly.
+  Logarithmic,
+  /// Linear cooling restarted `cycles` times, each one from a lower temperature.
+  Reheating { cycles: u32 },
 }
 
-const _: () = {
-  fn assert_send<T: Send>() {}
-  fn assert_sync<T: Sync>() {}
-  fn assert_all() {
-    assert_send::<SimulationOptions>();
-    assert_sync::<SimulationOptions>();
-  }
-};
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum InitialTemperature {
+  Fixed(f64),
+  /// Calibrated from random moves of the initial state, so that the average move that makes
+  /// the schedule worse is accepted with the given probability.
+  Auto {
+    acceptance_probability: f64,
+  },
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct AdvancedSimulationOptions {
-  pub progress_bar_update_interval: usize,
-  #[serde(skip)]
-  pub live_update: Option<LiveUpdate>,
+impl InitialTemperature {
+  pub const DEFAULT_TEMPERATURE: f64 = 3.0;
 }
 
-impl Default for AdvancedSimulationOptions {
+impl Default for InitialTemperature {
   fn default() -> Self {
-    Self {
-      progress_bar_update_interval: 100,
-      live_update: None,
-    }
+    InitialTemperature::Fixed(Self::DEFAULT_TEMPERATURE)
   }
 }
 
+/// When an annealing run stops. Only `Steps` and `Time` have a known length, so they are the
+/// ones that drive the temperature schedule and the reported progress. A run with neither stays
+/// at the initial temperature.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum StopCondition {
   Steps(usize),
-  #[deprecated]
   Time(Duration),
+  /// Stops once the best calendar has no hard constraint violations and costs at most this much.
+  /// Use `f64::INFINITY` to stop at the first feasible calendar.
+  TargetCost(f64),
+  /// Stops after this many steps in a row without improving the best calendar.
+  NoImprovement(usize),
+  /// Stops as soon as any of the conditions is met.
+  Any(Vec<StopCondition>),
+}
+
+/// How far a run has gone, to check it against a [`StopCondition`].
+struct RunProgress {
+  steps: usize,
+  elapsed: Duration,
+  best_cost: f64,
+  best_hard_constraint_violations: u64,
+  steps_since_improvement: usize,
+}
+
+impl StopCondition {
+  fn is_met(&self, progress: &RunProgress) -> bool {
+    match self {
+      StopCondition::Steps(total_steps) => progress.steps >= *total_steps,
+      StopCondition::Time(total_time) => progress.elapsed >= *total_time,
+      StopCondition::TargetCost(target_cost) => {
+        progress.best_hard_constraint_violations == 0 && progress.best_cost <= *target_cost
+      }
+      StopCondition::NoImprovement(steps) => progress.steps_since_improvement >= *steps,
+      StopCondition::Any(conditions) => conditions
+        .iter()
+        .any(|condition| condition.is_met(progress)),
+    }
+  }
+
+  /// Fraction of the run done, from the conditions with a known length.
+  fn fraction_done(&self, progress: &RunProgress) -> Option<f64> {
+    match self {
+      StopCondition::Steps(total_steps) => {
+        Some(((progress.steps + 1) as f64) / (*total_steps as f64))
+      }
+      StopCondition::Time(total_time) => {
+        Some((progress.elapsed.as_secs_f64() / total_time.as_secs_f64()).min(1.0))
+      }
+      StopCondition::TargetCost(_) | StopCondition::NoImprovement(_) => None,
+      StopCondition::Any(conditions) => conditions
+        .iter()
+        .filter_map(|condition| condition.fraction_done(progress))
+        .reduce(f64::max),
+    }
+  }
+
+  /// Most steps the run can take, if it has a step limit.
+  pub(crate) fn max_steps(&self) -> Option<usize> {
+    match self {
+      StopCondition::Steps(total_steps) => Some(*total_steps),
+      StopCondition::Any(conditions) => conditions
+        .iter()
+        .filter_map(|condition| condition.max_steps())
+        .min(),
+      _ => None,
+    }
+  }
+
+  /// Longest the run can take, if it has a time limit.
+  pub(crate) fn max_time(&self) -> Option<Duration> {
+    match self {
+      StopCondition::Time(total_time) => Some(*total_time),
+      StopCondition::Any(conditions) => conditions
+        .iter()
+        .filter_map(|condition| condition.max_time())
+        .min(),
+      _ => None,
+    }
+  }
 }
 
 impl Default for StopCondition {
@@ -401,36 +871,914 @@ impl Default for StopCondition {
   }
 }
 
-#[derive(Debug, Clone)]
-pub struct LiveUpdate {
-  pub channel: std::sync::mpsc::Sender<ClassCalendar>,
-  pub live_update_interval: usize,
+/// Kinds of moves the annealer makes. A session is the contiguous unlocked hours of a class in
+/// a day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, VariantArray)]
+enum MoveKind {
+  SingleHour,
+  SwapSessions,
+  Session,
+  DayShift,
+}
+
+/// Relative probability of each kind of move. They don't need to add up to one.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MoveProbabilities {
+  /// Move one hour of a class to a random day and time.
+  pub single_hour: f64,
+  /// Exchange the day and start time of two sessions of different classes.
+  pub swap_sessions: f64,
+  /// Move a whole session to a random day and time.
+  pub session: f64,
+  /// Move a whole session to the same time of another day.
+  pub day_shift: f64,
 }
 
+impl Default for MoveProbabilities {
+  fn default() -> Self {
+    Self {
+      single_hour: 0.7,
+      swap_sessions: 0.1,
+      session: 0.1,
+      day_shift: 0.1,
+    }
+  }
+}
+
+impl MoveProbabilities {
+  /// `None` if no move has a positive probability.
+  fn distribution(&self) -> Option<WeightedIndex<f64>> {
+    WeightedIndex::new(MoveKind::VARIANTS.iter().map(|move_kind| match move_kind {
+      MoveKind::SingleHour => self.single_hour,
+      MoveKind::SwapSessions => self.swap_sessions,
+      MoveKind::Session => self.session,
+      MoveKind::DayShift => self.day_shift,
+    }))
+    .ok()
+  }
+}
+
+/// Configuration of an annealing run, built from the initial state:
+///
+/// ```ignore
+/// let config = AnnealingConfig::new(initial_state)
+///   .stop_condition(StopCondition::Steps(100_000))
+///   .seed(42);
+/// ```
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct SimulationOptions {
-  pub stop_condition: StopCondition,
-  pub initial_state: ClassCalendar,
+pub struct AnnealingConfig {
+  initial_state: ClassCalendar,
+  stop_condition: StopCondition,
+  temperature_function: TemperatureFunction,
+  #[serde(default)]
+  initial_temperature: InitialTemperature,
+  #[serde(default)]
+  heuristic_weights: HeuristicWeights,
+  #[serde(default)]
+  move_probabilities: MoveProbabilities,
+  #[serde(default)]
+  seed: Option<u64>,
+  #[serde(skip)]
+  progress_observer: Option<Arc<dyn ProgressObserver>>,
+  #[serde(skip, default = "default_progress_interval")]
+  progress_interval: usize,
   #[serde(skip)]
-  pub progress: ProgressOption,
-  pub temperature_function: TemperatureFunction,
-  pub advanced_options: AdvancedSimulationOptions,
+  control: RunControl,
+}
+
+fn default_progress_interval() -> usize {
+  100
+}
+
+impl Default for AnnealingConfig {
+  fn default() -> Self {
+    Self {
+      initial_state: Default::default(),
+      stop_condition: Default::default(),
+      temperature_function: Default::default(),
+      initial_temperature: Default::default(),
+      heuristic_weights: Default::default(),
+      move_probabilities: Default::default(),
+      seed: None,
+      progress_observer: None,
+      progress_interval: default_progress_interval(),
+      control: Default::default(),
+    }
+  }
+}
+
+impl AnnealingConfig {
+  pub fn new(initial_state: ClassCalendar) -> Self {
+    Self {
+      initial_state,
+      ..Default::default()
+    }
+  }
+
+  pub fn stop_condition(mut self, stop_condition: StopCondition) -> Self {
+    self.stop_condition = stop_condition;
+    self
+  }
+
+  /// How the temperature goes down during the run.
+  pub fn temperature_schedule(mut self, temperature_function: TemperatureFunction) -> Self {
+    self.temperature_function = temperature_function;
+    self
+  }
+
+  pub fn initial_temperature(mut self, initial_temperature: InitialTemperature) -> Self {
+    self.initial_temperature = initial_temperature;
+    self
+  }
+
+  pub fn heuristic_weights(mut self, heuristic_weights: HeuristicWeights) -> Self {
+    self.heuristic_weights = heuristic_weights;
+    self
+  }
+
+  pub fn move_probabilities(mut self, move_probabilities: MoveProbabilities) -> Self {
+    self.move_probabilities = move_probabilities;
+    self
+  }
+
+  /// Seed of the random number generator. Without one a random seed is picked, which is stored
+  /// in the output so the run can be repeated.
+  pub fn seed(mut self, seed: u64) -> Self {
+    self.seed = Some(seed);
+    self
+  }
+
+  /// Reports the progress of the run to `observer`, see [`Self::progress_interval`].
+  pub fn progress_observer(mut self, observer: impl ProgressObserver + 'static) -> Self {
+    self.progress_observer = Some(Arc::new(observer));
+    self
+  }
+
+  /// Steps between progress reports, 100 by default.
+  pub fn progress_interval(mut self, steps: usize) -> Self {
+    self.progress_interval = steps.max(1);
+    self
+  }
+
+  /// Handle to stop or pause the run from another thread.
+  pub fn control(mut self, control: RunControl) -> Self {
+    self.control = control;
+    self
+  }
+}
+
+/// How much each heuristic adds to the cost of a schedule, per violation. The cost is divided
+/// by 1000, so a weight of 1000 makes each violation cost 1.0.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct HeuristicWeights {
+  pub classroom_assignment_collisions: u64,
+  pub professor_simultaneous_classes: u64,
+  pub semester_simultaneous_classes: u64,
+  pub labs_on_different_days: u64,
+  pub professor_not_available: u64,
+  pub incontinuous_classes: u64,
+  pub outside_session_length: u64,
+  pub holes_per_semester: u64,
+  pub professor_available_if_needed: u64,
+  pub inconsistent_class_timeslots: u64,
+  pub classroom_changes: u64,
+  pub simultaneous_classes: u64,
+}
+
+impl Default for HeuristicWeights {
+  fn default() -> Self {
+    Self {
+      classroom_assignment_collisions: 10000,
+      professor_simultaneous_classes: 9000,
+      semester_simultaneous_classes: 5000,
+      labs_on_different_days: 4500,
+      professor_not_available: 3000,
+      incontinuous_classes: 2500,
+      outside_session_length: 1500,
+      holes_per_semester: 1300,
+      professor_available_if_needed: 1250,
+      inconsistent_class_timeslots: 1000,
+      classroom_changes: 800,
+      simultaneous_classes: 100,
+    }
+  }
 }
 
-#[derive(Default, Debug)]
-pub struct SimulatedAnnealingOptimizer {}
+impl HeuristicWeights {
+  /// Cost of `state` per built-in heuristic.
+  pub fn cost_breakdown(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> Vec<HeuristicCost> {
+    cost_breakdown(state, constraints, &self.built_in_heuristics())
+  }
+
+  /// Violations of `state` for every built-in heuristic with a non-zero weight.
+  pub fn violations(
+    &self,
+    state: &ClassCalendar,
+    constraints: &OptimizationConstraints,
+  ) -> Vec<Violation> {
+    self
+      .built_in_heuristics()
+      .iter()
+      .filter(|weighted_heuristic| weighted_heuristic.weight > 0)
+      .flat_map(|weighted_heuristic| weighted_heuristic.heuristic.violations(state, constraints))
+      .collect()
+  }
+
+  pub(crate) fn built_in_heuristics(&self) -> Vec<WeightedHeuristic> {
+    vec![
+      WeightedHeuristic::new(
+        heuristics::ClassroomAssignmentCollisions,
+        self.classroom_assignment_collisions,
+      ),
+      WeightedHeuristic::new(
+        heuristics::ProfessorSimultaneousClasses,
+        self.professor_simultaneous_classes,
+      ),
+      WeightedHeuristic::new(
+        heuristics::SemesterSimultaneousClasses,
+        self.semester_simultaneous_classes,
+      ),
+      WeightedHeuristic::new(heuristics::LabsOnDifferentDays, self.labs_on_different_days),
+      WeightedHeuristic::new(
+        heuristics::ProfessorNotAvailable,
+        self.professor_not_available,
+      ),
+      WeightedHeuristic::new(heuristics::IncontinuousClasses, self.incontinuous_classes),
+      WeightedHeuristic::new(
+        heuristics::OutsideSessionLength {
+          min_session_length: 2,
+          max_session_length: 4,
+        },
+        self.outside_session_length,
+      ),
+      WeightedHeuristic::new(heuristics::HolesPerSemester, self.holes_per_semester),
+      WeightedHeuristic::new(
+        heuristics::ProfessorAvailableIfNeeded,
+        self.professor_available_if_needed,
+      ),
+      WeightedHeuristic::new(
+        heuristics::InconsistentClassTimeslots,
+        self.inconsistent_class_timeslots,
+      ),
+      WeightedHeuristic::new(heuristics::ClassroomChanges, self.classroom_changes),
+      WeightedHeuristic::new(heuristics::SimultaneousClasses, self.simultaneous_classes),
+    ]
+  }
+}
+
+#[derive(Default)]
+pub struct SimulatedAnnealingOptimizer {
+  custom_heuristics: Vec<WeightedHeuristic>,
+}
+
+impl SimulatedAnnealingOptimizer {
+  /// Adds a heuristic to the cost function, on top of the built-in ones. `weight` works the same
+  /// as in [`HeuristicWeights`].
+  pub fn register_heuristic(&mut self, heuristic: impl Heuristic + 'static, weight: u64) {
+    self
+      .custom_heuristics
+      .push(WeightedHeuristic::new(heuristic, weight));
+  }
+
+  /// Runs a single annealing chain.
+  pub fn run(
+    &self,
+    constraints: &OptimizationConstraints,
+    config: AnnealingConfig,
+  ) -> SimulationOutput {
+    simulated_annealing(constraints, config, &self.custom_heuristics)
+  }
+
+  /// Runs `runs` independent annealing chains from the same initial state, each on its own
+  /// thread and with the seed after the previous chain's. Outputs are ranked best first by their best
+  /// calendar: by hard constraint violations, then by cost. The progress of the chains is merged
+  /// into a single report, with the best calendar of any of them.
+  pub fn run_multi_start(
+    &self,
+    constraints: &OptimizationConstraints,
+    options: AnnealingConfig,
+    runs: usize,
+  ) -> Vec<SimulationOutput> {
+    let seed = options.seed.unwrap_or_else(|| thread_rng().gen());
+    let combined_progress = options
+      .progress_observer
+      .clone()
+      .map(|observer| CombinedProgress::new(observer, runs));
+    let mut outputs = std::thread::scope(|scope| {
+      let handles = (0..runs)
+        .map(|run| {
+          let options = AnnealingConfig {
+            progress_observer: combined_progress.as_ref().map(|combined_progress| {
+              Arc::new(combined_progress.run_observer(run)) as Arc<dyn ProgressObserver>
+            }),
+            seed: Some(seed.wrapping_add(run as u64)),
+            ..options.clone()
+          };
+          scope.spawn(move || simulated_annealing(constraints, options, &self.custom_heuristics))
+        })
+        .collect_vec();
+      handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect_vec()
+    });
+    outputs.sort_by(|a, b| {
+      a.best_hard_constraint_violations
+        .total()
+        .cmp(&b.best_hard_constraint_violations.total())
+        .then(a.best_cost.total_cmp(&b.best_cost))
+    });
+    outputs
+  }
+}
+
+impl std::fmt::Debug for SimulatedAnnealingOptimizer {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("SimulatedAnnealingOptimizer")
+      .field(
+        "custom_heuristics",
+        &self
+          .custom_heuristics
+          .iter()
+          .map(|weighted_heuristic| weighted_heuristic.heuristic.name())
+          .collect_vec(),
+      )
+      .finish()
+  }
+}
 
 impl ClassCalendarOptimizer for SimulatedAnnealingOptimizer {
-  type OptimizerOptions = SimulationOptions;
+  type OptimizerOptions = AnnealingConfig;
 
   fn generate_class_calendar(
     &mut self,
     constraints: crate::OptimizationConstraints,
     options: Self::OptimizerOptions,
-    cost_function: Option<crate::CostFunction>,
   ) -> crate::ClassCalendar {
-    let mut rng = thread_rng();
-    let result = simulated_annealing(&constraints, options, &mut rng);
-    result.final_calendar
+    let result = simulated_annealing(&constraints, options, &self.custom_heuristics);
+    result.best_calendar
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::sync::{atomic::AtomicUsize, Mutex};
+
+  use crate::{Availability, SchoolSchedule, DAY_MONDAY, TIMESLOT_08_00, TIMESLOT_10_00};
+
+  use super::*;
+
+  #[test]
+  fn test_hard_constraints_never_increase() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type_key = schedule.add_new_classroom_type();
+    schedule.add_new_classroom(classroom_type_key);
+    let professor_key = schedule.add_new_professor();
+    let week_layout = schedule.week_layout().clone();
+    for day in week_layout.days() {
+      for timeslot in week_layout.timeslots() {
+        *schedule
+          .get_professor_mut(professor_key)
+          .unwrap()
+          .availability
+          .get_mut(day, timeslot) = if (day, timeslot) == (DAY_MONDAY, TIMESLOT_08_00) {
+          Availability::Available
+        } else {
+          Availability::NotAvailable
+        };
+      }
+    }
+    let class_key = schedule.add_new_class(professor_key);
+    let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+    class_entry.set_allowed_classroom_types([classroom_type_key].into());
+    class_entry.set_hours(1);
+
+    let output = simulated_annealing(
+      schedule.get_simulation_constraints(),
+      AnnealingConfig::new(schedule.class_calendar().clone())
+        .stop_condition(StopCondition::Steps(1_000)),
+      &[],
+    );
+    assert!(output.hard_constraint_violations.is_feasible());
+    assert_eq!(
+      output
+        .final_calendar
+        .get_count(DAY_MONDAY, TIMESLOT_08_00, class_key),
+      1
+    );
+  }
+
+  #[test]
+  fn test_heuristic_weights() {
+    let mut schedule = SchoolSchedule::default();
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..2 {
+      let class_key = schedule.add_new_class(professor_key);
+      schedule.get_class_entry(class_key).unwrap().set_hours(2);
+    }
+    let options = AnnealingConfig::new(schedule.class_calendar().clone())
+      .stop_condition(StopCondition::Steps(0));
+    let output = simulated_annealing(schedule.get_simulation_constraints(), options.clone(), &[]);
+    assert!(output.final_cost > 0.0);
+
+    let options = options.heuristic_weights(HeuristicWeights {
+      simultaneous_classes: 1000,
+      ..zero_heuristic_weights()
+    });
+    let output = simulated_annealing(schedule.get_simulation_constraints(), options, &[]);
+    // Four class hours in the same timeslot
+    assert_eq!(output.final_cost, 4.0);
+  }
+
+  #[test]
+  fn test_cost_breakdown() {
+    let mut schedule = SchoolSchedule::default();
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..3 {
+      let class_key = schedule.add_new_class(professor_key);
+      schedule.get_class_entry(class_key).unwrap().set_hours(3);
+    }
+    let output = simulated_annealing(
+      schedule.get_simulation_constraints(),
+      AnnealingConfig::new(schedule.class_calendar().clone())
+        .stop_condition(StopCondition::Steps(100)),
+      &[],
+    );
+    assert_eq!(
+      output.cost_breakdown,
+      HeuristicWeights::default().cost_breakdown(
+        &output.final_calendar,
+        schedule.get_simulation_constraints()
+      )
+    );
+    let total_cost: f64 = output.cost_breakdown.iter().map(|c| c.cost).sum();
+    assert!((total_cost - output.final_cost).abs() < 1e-9);
+    let simultaneous_classes = output
+      .cost_breakdown
+      .iter()
+      .find(|c| c.name == "simultaneous_classes")
+      .unwrap();
+    assert_eq!(simultaneous_classes.weight, 100);
+    assert_eq!(
+      simultaneous_classes.cost,
+      (simultaneous_classes.violations * 100) as f64 / 1000.0
+    );
+    assert_eq!(
+      output.stats["cost_simultaneous_classes"].len(),
+      output.stats["curr_cost"].len()
+    );
+  }
+
+  #[test]
+  fn test_best_calendar() {
+    let mut schedule = SchoolSchedule::default();
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..3 {
+      let class_key = schedule.add_new_class(professor_key);
+      schedule.get_class_entry(class_key).unwrap().set_hours(3);
+    }
+    let constraints = schedule.get_simulation_constraints();
+    let initial_cost: f64 = HeuristicWeights::default()
+      .cost_breakdown(schedule.class_calendar(), constraints)
+      .iter()
+      .map(|c| c.cost)
+      .sum();
+    // A high temperature keeps the run wandering, so it rarely ends at its best state.
+    let output = simulated_annealing(
+      constraints,
+      AnnealingConfig::new(schedule.class_calendar().clone())
+        .stop_condition(StopCondition::Steps(500))
+        .initial_temperature(InitialTemperature::Fixed(1000.0))
+        .seed(0),
+      &[],
+    );
+    assert!(output.best_cost <= output.final_cost);
+    assert!(output.best_cost < initial_cost);
+    let best_cost: f64 = HeuristicWeights::default()
+      .cost_breakdown(&output.best_calendar, constraints)
+      .iter()
+      .map(|c| c.cost)
+      .sum();
+    assert!((best_cost - output.best_cost).abs() < 1e-9);
+    assert_eq!(
+      output.best_hard_constraint_violations,
+      HardConstraintViolations::count(&output.best_calendar, constraints)
+    );
+  }
+
+  #[test]
+  fn test_stop_conditions() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type_key = schedule.add_new_classroom_type();
+    schedule.add_new_classroom(classroom_type_key);
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..3 {
+      let class_key = schedule.add_new_class(professor_key);
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_allowed_classroom_types([classroom_type_key].into());
+      class_entry.set_hours(2);
+    }
+    let constraints = schedule.get_simulation_constraints();
+    assert!(!schedule.hard_constraint_violations().is_feasible());
+    let options = AnnealingConfig::new(schedule.class_calendar().clone())
+      .stop_condition(StopCondition::Any(vec![
+        StopCondition::Steps(1_000_000),
+        StopCondition::TargetCost(f64::INFINITY),
+      ]))
+      .seed(0);
+    let output = simulated_annealing(constraints, options.clone(), &[]);
+    assert!(output.best_hard_constraint_violations.is_feasible());
+    assert!(output.total_steps < 1_000_000);
+
+    // No step limit at all
+    let output = simulated_annealing(
+      constraints,
+      options.stop_condition(StopCondition::NoImprovement(200)),
+      &[],
+    );
+    assert!(output.total_steps >= 200);
+    let curr_cost = &output.stats["curr_cost"];
+    assert!(!curr_cost.is_empty());
+  }
+
+  #[test]
+  fn test_progress_observer() {
+    let mut schedule = SchoolSchedule::default();
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..3 {
+      let class_key = schedule.add_new_class(professor_key);
+      schedule.get_class_entry(class_key).unwrap().set_hours(2);
+    }
+    let best_costs = Arc::new(RwLock::new(Vec::new()));
+    let callback_best_costs = best_costs.clone();
+    let output = SimulatedAnnealingOptimizer::default().run(
+      schedule.get_simulation_constraints(),
+      AnnealingConfig::new(schedule.class_calendar().clone())
+        .stop_condition(StopCondition::Steps(100))
+        .progress_interval(10)
+        .progress_observer(move |progress: &Progress| {
+          callback_best_costs
+            .write()
+            .unwrap()
+            .push((progress.step, progress.best_cost));
+        }),
+    );
+    let best_costs = best_costs.read().unwrap();
+    // The last report comes from the end of the run
+    assert_eq!(
+      best_costs.iter().map(|(step, _)| *step).collect_vec(),
+      (0..=100).step_by(10).collect_vec()
+    );
+    assert!(best_costs
+      .iter()
+      .tuple_windows()
+      .all(|((_, a), (_, b))| b <= a));
+    assert_eq!(output.best_cost, best_costs.last().unwrap().1);
+  }
+
+  #[test]
+  fn test_run_multi_start() {
+    #[derive(Default)]
+    struct Reports {
+      /// Fraction done and best cost of every report.
+      progress: Mutex<Vec<(Option<f64>, f64)>>,
+      finishes: AtomicUsize,
+    }
+
+    impl ProgressObserver for Arc<Reports> {
+      fn on_progress(&self, progress: &Progress) {
+        self
+          .progress
+          .lock()
+          .unwrap()
+          .push((progress.fraction_done, progress.best_cost));
+      }
+
+      fn on_finish(&self, progress: &Progress) {
+        self.on_progress(progress);
+        self.finishes.fetch_add(1, Ordering::SeqCst);
+      }
+    }
+
+    let mut schedule = SchoolSchedule::default();
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..3 {
+      let class_key = schedule.add_new_class(professor_key);
+      schedule.get_class_entry(class_key).unwrap().set_hours(2);
+    }
+    let reports = Arc::new(Reports::default());
+    let outputs = SimulatedAnnealingOptimizer::default().run_multi_start(
+      schedule.get_simulation_constraints(),
+      AnnealingConfig::new(schedule.class_calendar().clone())
+        .stop_condition(StopCondition::Steps(50))
+        .progress_interval(10)
+        .progress_observer(reports.clone())
+        .seed(0),
+      3,
+    );
+    assert_eq!(outputs.len(), 3);
+    for (a, b) in outputs.iter().tuple_windows() {
+      assert!(
+        (a.best_hard_constraint_violations.total(), a.best_cost)
+          <= (b.best_hard_constraint_violations.total(), b.best_cost)
+      );
+    }
+
+    // The reports cover every chain
+    assert_eq!(reports.finishes.load(Ordering::SeqCst), 1);
+    let progress = reports.progress.lock().unwrap();
+    assert_eq!(progress.len(), 3 * 6);
+    assert!(progress
+      .iter()
+      .tuple_windows()
+      .all(|((a, _), (b, _))| a <= b));
+    assert_eq!(*progress.last().unwrap(), (Some(1.0), outputs[0].best_cost));
+  }
+
+  #[test]
+  fn test_seed_reproducible() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type_key = schedule.add_new_classroom_type();
+    schedule.add_new_classroom(classroom_type_key);
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..3 {
+      let class_key = schedule.add_new_class(professor_key);
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_allowed_classroom_types([classroom_type_key].into());
+      class_entry.set_hours(3);
+    }
+    let options = AnnealingConfig::new(schedule.class_calendar().clone())
+      .stop_condition(StopCondition::Steps(300))
+      .initial_temperature(InitialTemperature::Auto {
+        acceptance_probability: 0.8,
+      })
+      .seed(42);
+    let a = simulated_annealing(schedule.get_simulation_constraints(), options.clone(), &[]);
+    let b = simulated_annealing(schedule.get_simulation_constraints(), options, &[]);
+    assert_eq!(a.seed, 42);
+    assert_eq!(
+      a.final_calendar.class_entries(),
+      b.final_calendar.class_entries()
+    );
+    assert_eq!(a.final_cost, b.final_cost);
+
+    // A random seed is stored, and running again with it gives the same calendar.
+    let options = AnnealingConfig {
+      seed: None,
+      ..a.simulation_options.clone()
+    };
+    let c = simulated_annealing(schedule.get_simulation_constraints(), options.clone(), &[]);
+    let d = simulated_annealing(
+      schedule.get_simulation_constraints(),
+      options.seed(c.seed),
+      &[],
+    );
+    assert_eq!(
+      c.final_calendar.class_entries(),
+      d.final_calendar.class_entries()
+    );
+  }
+
+  #[test]
+  fn test_run_control() {
+    let mut schedule = SchoolSchedule::default();
+    let professor_key = schedule.add_new_professor();
+    let class_key = schedule.add_new_class(professor_key);
+    schedule.get_class_entry(class_key).unwrap().set_hours(3);
+    let control = RunControl::default();
+    let paused = Arc::new(Barrier::new(2));
+    let last_step = Arc::new(AtomicUsize::new(0));
+    let observer_control = control.clone();
+    let observer_paused = paused.clone();
+    let observer_last_step = last_step.clone();
+    let options = AnnealingConfig::new(schedule.class_calendar().clone())
+      .stop_condition(StopCondition::Steps(usize::MAX))
+      .control(control.clone())
+      .progress_interval(1)
+      .progress_observer(move |progress: &Progress| {
+        observer_last_step.store(progress.step, Ordering::SeqCst);
+        match progress.step {
+          10 => {
+            observer_control.pause();
+            observer_paused.wait();
+          }
+          // Stopping also ends a paused run
+          20 => {
+            observer_control.pause();
+            observer_control.stop();
+          }
+          _ => (),
+        }
+      });
+    let constraints = schedule.get_simulation_constraints().clone();
+    let handle = std::thread::spawn(move || simulated_annealing(&constraints, options, &[]));
+    paused.wait();
+    assert!(control.is_paused());
+    // The run doesn't take another step until it's resumed
+    assert_eq!(last_step.load(Ordering::SeqCst), 10);
+    assert!(!handle.is_finished());
+    control.resume();
+    let output = handle.join().unwrap();
+    control.resume();
+    assert!(control.is_stopped());
+    assert_eq!(output.total_steps, 21);
+    assert_eq!(output.final_calendar.class_entries().len(), 3);
+  }
+
+  #[test]
+  fn test_temperature_functions() {
+    for temperature_function in [
+      TemperatureFunction::Linear,
+      TemperatureFunction::Exponential,
+      TemperatureFunction::Logarithmic,
+      TemperatureFunction::Reheating { cycles: 3 },
+    ] {
+      assert_eq!(temperature(0.0, &temperature_function, 3.0), 3.0);
+      assert!(temperature(1.0, &temperature_function, 3.0) < 0.5);
+    }
+    let t = |x| temperature(x, &TemperatureFunction::Exponential, 1.0);
+    assert!(t(0.2) > t(0.4) && t(0.4) > t(0.6));
+    let t = |x| temperature(x, &TemperatureFunction::Logarithmic, 1.0);
+    assert!(t(0.2) > t(0.4) && t(0.4) > t(0.6));
+    // Cools down and heats up again, but less than before.
+    let t = |x| temperature(x, &TemperatureFunction::Reheating { cycles: 2 }, 1.0);
+    assert!(t(0.25) > t(0.45));
+    assert!(t(0.55) > t(0.45));
+    assert!(t(0.55) < t(0.05));
+  }
+
+  #[test]
+  fn test_time_stop_condition() {
+    let mut schedule = SchoolSchedule::default();
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..3 {
+      let class_key = schedule.add_new_class(professor_key);
+      schedule.get_class_entry(class_key).unwrap().set_hours(2);
+    }
+    let temperatures = Arc::new(RwLock::new(Vec::new()));
+    let callback_temperatures = temperatures.clone();
+    simulated_annealing(
+      schedule.get_simulation_constraints(),
+      AnnealingConfig::new(schedule.class_calendar().clone())
+        .stop_condition(StopCondition::Time(Duration::from_millis(200)))
+        .temperature_schedule(TemperatureFunction::Linear)
+        .initial_temperature(InitialTemperature::Fixed(3.0))
+        .progress_interval(1)
+        .progress_observer(move |progress: &Progress| {
+          if let Some(temperature) = progress.temperature {
+            callback_temperatures.write().unwrap().push(temperature);
+          }
+        }),
+      &[],
+    );
+    let temperatures = temperatures.read().unwrap();
+    assert!(!temperatures.is_empty());
+    assert!(temperatures.iter().all(|t| (0.0..=3.0).contains(t)));
+
+    // The run cools down as time passes, checked at fixed elapsed times so it doesn't depend on
+    // how fast the run goes.
+    let stop_condition = StopCondition::Time(Duration::from_millis(200));
+    let run_progress = |elapsed| RunProgress {
+      steps: 0,
+      elapsed: Duration::from_millis(elapsed),
+      best_cost: 0.0,
+      best_hard_constraint_violations: 0,
+      steps_since_improvement: 0,
+    };
+    assert!(!stop_condition.is_met(&run_progress(0)));
+    assert!(!stop_condition.is_met(&run_progress(199)));
+    assert!(stop_condition.is_met(&run_progress(200)));
+    assert_eq!(stop_condition.fraction_done(&run_progress(0)), Some(0.0));
+    assert_eq!(stop_condition.fraction_done(&run_progress(300)), Some(1.0));
+    let halfway = stop_condition.fraction_done(&run_progress(100)).unwrap();
+    assert_eq!(halfway, 0.5);
+    assert!((1.0..=2.0).contains(&temperature(halfway, &TemperatureFunction::Linear, 3.0)));
+  }
+
+  #[test]
+  fn test_par_evaluator_joins_threads() {
+    let mut schedule = SchoolSchedule::default();
+    let professor_key = schedule.add_new_professor();
+    let class_key = schedule.add_new_class(professor_key);
+    schedule.get_class_entry(class_key).unwrap().set_hours(2);
+    let par_eval = ParEvaluator::new(
+      schedule.class_calendar().clone(),
+      schedule.get_simulation_constraints().clone(),
+      HeuristicWeights::default().built_in_heuristics(),
+    );
+    let costs = par_eval.costs.clone();
+    assert!(Arc::strong_count(&costs) > 2);
+    drop(par_eval);
+    // Every evaluator thread has exited and released its handle
+    assert_eq!(Arc::strong_count(&costs), 1);
+  }
+
+  #[test]
+  fn test_calibrate_temperature() {
+    let mut schedule = SchoolSchedule::default();
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..3 {
+      let class_key = schedule.add_new_class(professor_key);
+      schedule.get_class_entry(class_key).unwrap().set_hours(3);
+    }
+    let constraints = schedule.get_simulation_constraints();
+    // Spread the classes, so that most moves make the schedule worse.
+    let mut state = schedule.class_calendar().clone();
+    let entries = state.class_entries().clone();
+    for (entry, (day, timeslot)) in entries.iter().zip(
+      state
+        .week_layout()
+        .days()
+        .flat_map(|day| [TIMESLOT_08_00, TIMESLOT_10_00].map(|timeslot| (day, timeslot))),
+    ) {
+      state.move_one_class(entry.day, entry.timeslot, day, timeslot, entry.class_key);
+    }
+    let heuristics = HeuristicWeights::default().built_in_heuristics();
+    let mut par_eval = ParEvaluator::new(state.clone(), constraints.clone(), heuristics);
+    let hard_constraint_violations = HardConstraintViolations::count(&state, constraints);
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let mut calibrate = |par_eval: &mut ParEvaluator, state: &mut ClassCalendar, p: f64| {
+      calibrate_temperature(
+        par_eval,
+        state,
+        &hard_constraint_violations,
+        &MoveProbabilities::default().distribution(),
+        p,
+        &mut rng,
+      )
+    };
+    let high = calibrate(&mut par_eval, &mut state, 0.9);
+    let low = calibrate(&mut par_eval, &mut state, 0.1);
+    assert!(high > low && low > 0.0);
+    // The evaluator is back at the state it started from
+    assert_eq!(
+      par_eval.costs().collect_vec(),
+      HeuristicWeights::default()
+        .cost_breakdown(&state, constraints)
+        .iter()
+        .map(|heuristic_cost| heuristic_cost.cost)
+        .collect_vec()
+    );
+  }
+
+  fn zero_heuristic_weights() -> HeuristicWeights {
+    HeuristicWeights {
+      classroom_assignment_collisions: 0,
+      professor_simultaneous_classes: 0,
+      semester_simultaneous_classes: 0,
+      labs_on_different_days: 0,
+      professor_not_available: 0,
+      incontinuous_classes: 0,
+      outside_session_length: 0,
+      holes_per_semester: 0,
+      professor_available_if_needed: 0,
+      inconsistent_class_timeslots: 0,
+      classroom_changes: 0,
+      simultaneous_classes: 0,
+    }
+  }
+
+  struct ClassesOnMonday;
+
+  impl Heuristic for ClassesOnMonday {
+    fn name(&self) -> &str {
+      "classes_on_monday"
+    }
+
+    fn evaluate(&self, state: &ClassCalendar, _constraints: &OptimizationConstraints) -> u64 {
+      state
+        .class_entries()
+        .iter()
+        .filter(|class_entry| class_entry.day == DAY_MONDAY)
+        .count() as u64
+    }
+  }
+
+  #[test]
+  fn test_custom_heuristic() {
+    let mut schedule = SchoolSchedule::default();
+    let professor_key = schedule.add_new_professor();
+    let class_key = schedule.add_new_class(professor_key);
+    schedule.get_class_entry(class_key).unwrap().set_hours(3);
+    let output = simulated_annealing(
+      schedule.get_simulation_constraints(),
+      AnnealingConfig::new(schedule.class_calendar().clone())
+        .stop_condition(StopCondition::Steps(0))
+        .heuristic_weights(zero_heuristic_weights()),
+      &[WeightedHeuristic::new(ClassesOnMonday, 500)],
+    );
+    assert_eq!(output.final_cost, 1.5);
+
+    let mut optimizer = SimulatedAnnealingOptimizer::default();
+    optimizer.register_heuristic(ClassesOnMonday, 500);
+    assert_eq!(
+      format!("{optimizer:?}"),
+      "SimulatedAnnealingOptimizer { custom_heuristics: [\"classes_on_monday\"] }"
+    );
   }
 }
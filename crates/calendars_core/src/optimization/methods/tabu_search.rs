@@ -0,0 +1,393 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  sync::Arc,
+};
+
+use itertools::Itertools;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  classroom_assignment::assign_classrooms,
+  optimization::{
+    class_calendar::{ClassEntryDelta, MoveOneClassRandomError},
+    hard_constraints::HardConstraintViolations,
+    heuristics::Heuristic,
+    progress::{Progress, ProgressObserver},
+  },
+  school_schedule::{classroom_assignments_serde, ClassroomAssignmentKey},
+  week_calendar, ClassCalendar, ClassCalendarOptimizer, ClassKey, ClassroomKey, HeuristicCost,
+  HeuristicWeights, OptimizationConstraints,
+};
+
+use super::simulated_annealing::{
+  cost_breakdown, RunControl, WeightedHeuristic, EVALUATORS_FACTOR,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TabuSearchOptions {
+  pub initial_state: ClassCalendar,
+  /// Number of moves to make.
+  pub iterations: usize,
+  /// Number of random moves compared in every iteration.
+  pub neighborhood_size: usize,
+  /// Number of iterations during which a class can't go back to the timeslot it left.
+  pub tabu_tenure: usize,
+  #[serde(default)]
+  pub heuristic_weights: HeuristicWeights,
+  /// Seed of the random moves. `None` uses a random one.
+  #[serde(default)]
+  pub seed: Option<u64>,
+  /// Reported after every iteration.
+  #[serde(skip)]
+  pub progress: Option<Arc<dyn ProgressObserver>>,
+  #[serde(skip)]
+  pub control: RunControl,
+}
+
+impl Default for TabuSearchOptions {
+  fn default() -> Self {
+    Self {
+      initial_state: Default::default(),
+      iterations: 1_000,
+      neighborhood_size: 50,
+      tabu_tenure: 20,
+      heuristic_weights: Default::default(),
+      seed: None,
+      progress: None,
+      control: Default::default(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TabuSearchOutput {
+  /// Seed the run used. Running again with it and the same options gives the same calendar.
+  pub seed: u64,
+  pub start_time: std::time::SystemTime,
+  pub end_time: std::time::SystemTime,
+  pub duration: std::time::Duration,
+  /// Iterations made, fewer than asked if the run was stopped or had no moves left.
+  pub total_steps: usize,
+  pub final_calendar: ClassCalendar,
+  pub final_cost: f64,
+  /// Lowest cost calendar visited during the run, among the ones with the fewest hard constraint
+  /// violations.
+  pub best_calendar: ClassCalendar,
+  pub best_cost: f64,
+  pub best_hard_constraint_violations: HardConstraintViolations,
+  /// Cost of `best_calendar` per heuristic.
+  pub cost_breakdown: Vec<HeuristicCost>,
+  /// Classrooms of `best_calendar`.
+  #[serde(with = "classroom_assignments_serde")]
+  pub classroom_assignments: BTreeMap<ClassroomAssignmentKey, ClassroomKey>,
+}
+
+/// A candidate move and the state it leads to.
+struct Neighbor {
+  delta: ClassEntryDelta,
+  costs: Vec<u64>,
+  hard_constraint_violations: HardConstraintViolations,
+}
+
+impl Neighbor {
+  fn cost(&self) -> u64 {
+    self.costs.iter().sum()
+  }
+
+  /// Neighbors are ranked by hard constraint violations first, then by cost.
+  fn rank(&self) -> (u64, u64) {
+    (self.hard_constraint_violations.total(), self.cost())
+  }
+}
+
+fn apply_change(state: &mut ClassCalendar, delta: &ClassEntryDelta) {
+  state.move_one_class(
+    delta.src_day,
+    delta.src_timeslot,
+    delta.dst_day,
+    delta.dst_timeslot,
+    delta.class_key,
+  );
+}
+
+fn revert_change(state: &mut ClassCalendar, delta: &ClassEntryDelta) {
+  state.move_one_class(
+    delta.dst_day,
+    delta.dst_timeslot,
+    delta.src_day,
+    delta.src_timeslot,
+    delta.class_key,
+  );
+}
+
+/// Every iteration moves to the best of `neighborhood_size` random neighbors, even if it's
+/// worse than the current state. Moving a class back to a timeslot it recently left is tabu,
+/// unless it leads to a new best state (aspiration). Like in annealing, moves that add hard
+/// constraint violations are never made.
+fn tabu_search(
+  constraints: &OptimizationConstraints,
+  options: TabuSearchOptions,
+  custom_heuristics: &[WeightedHeuristic],
+) -> TabuSearchOutput {
+  let seed = options.seed.unwrap_or_else(|| thread_rng().gen());
+  let mut rng = ChaCha8Rng::seed_from_u64(seed);
+  let start_time = std::time::SystemTime::now();
+
+  let all_heuristics = options
+    .heuristic_weights
+    .built_in_heuristics()
+    .into_iter()
+    .chain(custom_heuristics.iter().cloned())
+    .collect_vec();
+  let heuristics = all_heuristics
+    .iter()
+    .filter(|weighted_heuristic| weighted_heuristic.weight > 0)
+    .cloned()
+    .collect_vec();
+
+  let mut state = options.initial_state;
+  // Copy of `state` in which neighbors are evaluated.
+  let mut neighbor_state = state.clone();
+  let mut state_costs = heuristics
+    .iter()
+    .map(|heuristic| heuristic.evaluate(&state, constraints))
+    .collect_vec();
+  let mut state_hard_constraint_violations = HardConstraintViolations::count(&state, constraints);
+  let mut best_calendar = state.clone();
+  let mut best_cost: u64 = state_costs.iter().sum();
+  let mut best_hard_constraint_violations = state_hard_constraint_violations;
+
+  // Iteration until which moving a class into a timeslot is tabu.
+  let mut tabu_list: HashMap<(ClassKey, week_calendar::Day, week_calendar::Timeslot), usize> =
+    HashMap::new();
+
+  let start_instant = std::time::Instant::now();
+  let mut completed_iterations = 0;
+
+  'search: for iteration in 0..options.iterations {
+    if !options.control.wait_while_paused() {
+      break;
+    }
+    let mut best_neighbor: Option<Neighbor> = None;
+    for _ in 0..options.neighborhood_size {
+      let delta = match state.move_one_class_random_delta(&mut rng) {
+        Ok((delta, _entry_index)) => delta,
+        // Every class is locked (or there are none), nothing left to optimize.
+        Err(MoveOneClassRandomError::NoClassesToMove) => break 'search,
+        Err(MoveOneClassRandomError::RandomChosenDestinationFull) => continue,
+      };
+      if (delta.src_day, delta.src_timeslot) == (delta.dst_day, delta.dst_timeslot) {
+        continue;
+      }
+
+      apply_change(&mut neighbor_state, &delta);
+      let hard_constraint_violations =
+        state_hard_constraint_violations.with_delta(&state, &neighbor_state, constraints, &delta);
+      let neighbor = hard_constraint_violations
+        .within(&state_hard_constraint_violations)
+        .then(|| Neighbor {
+          costs: heuristics
+            .iter()
+            .zip(state_costs.iter())
+            .map(|(heuristic, state_cost)| {
+              heuristic
+                .evaluate_delta(&state, &neighbor_state, constraints, &delta)
+                .map(|cost_delta| state_cost.checked_add_signed(cost_delta).unwrap())
+                .unwrap_or_else(|| heuristic.evaluate(&neighbor_state, constraints))
+            })
+            .collect(),
+          delta: delta.clone(),
+          hard_constraint_violations,
+        });
+      revert_change(&mut neighbor_state, &delta);
+
+      let Some(neighbor) = neighbor else {
+        continue;
+      };
+      let is_tabu = tabu_list
+        .get(&(delta.class_key, delta.dst_day, delta.dst_timeslot))
+        .is_some_and(|tabu_until| iteration < *tabu_until);
+      let aspiration = neighbor.rank() < (best_hard_constraint_violations.total(), best_cost);
+      if is_tabu && !aspiration {
+        continue;
+      }
+      if best_neighbor
+        .as_ref()
+        .is_none_or(|best_neighbor| neighbor.rank() < best_neighbor.rank())
+      {
+        best_neighbor = Some(neighbor);
+      }
+    }
+
+    if let Some(neighbor) = best_neighbor {
+      let delta = neighbor.delta;
+      apply_change(&mut state, &delta);
+      apply_change(&mut neighbor_state, &delta);
+      tabu_list.insert(
+        (delta.class_key, delta.src_day, delta.src_timeslot),
+        iteration + options.tabu_tenure,
+      );
+      state_costs = neighbor.costs;
+      state_hard_constraint_violations = neighbor.hard_constraint_violations;
+      let state_cost = state_costs.iter().sum();
+      if (state_hard_constraint_violations.total(), state_cost)
+        < (best_hard_constraint_violations.total(), best_cost)
+      {
+        best_cost = state_cost;
+        best_calendar = state.clone();
+        best_hard_constraint_violations = state_hard_constraint_violations;
+      }
+    }
+
+    completed_iterations = iteration + 1;
+    if let Some(observer) = options.progress.as_ref() {
+      observer.on_progress(&Progress {
+        step: completed_iterations,
+        total_steps: Some(options.iterations),
+        fraction_done: Some(completed_iterations as f64 / options.iterations as f64),
+        elapsed: start_instant.elapsed(),
+        cost: state_costs.iter().sum::<u64>() as f64 / EVALUATORS_FACTOR as f64,
+        temperature: None,
+        best_cost: best_cost as f64 / EVALUATORS_FACTOR as f64,
+        best_hard_constraint_violations: best_hard_constraint_violations.total(),
+        best_calendar: &best_calendar,
+      });
+    }
+  }
+
+  if let Some(observer) = options.progress.as_ref() {
+    observer.on_finish(&Progress {
+      step: completed_iterations,
+      total_steps: Some(options.iterations),
+      fraction_done: Some(completed_iterations as f64 / options.iterations as f64),
+      elapsed: start_instant.elapsed(),
+      cost: state_costs.iter().sum::<u64>() as f64 / EVALUATORS_FACTOR as f64,
+      temperature: None,
+      best_cost: best_cost as f64 / EVALUATORS_FACTOR as f64,
+      best_hard_constraint_violations: best_hard_constraint_violations.total(),
+      best_calendar: &best_calendar,
+    });
+  }
+
+  TabuSearchOutput {
+    seed,
+    start_time,
+    end_time: std::time::SystemTime::now(),
+    duration: start_instant.elapsed(),
+    total_steps: completed_iterations,
+    final_cost: state_costs.iter().sum::<u64>() as f64 / EVALUATORS_FACTOR as f64,
+    final_calendar: state,
+    best_cost: best_cost as f64 / EVALUATORS_FACTOR as f64,
+    best_hard_constraint_violations,
+    cost_breakdown: cost_breakdown(&best_calendar, constraints, &all_heuristics),
+    classroom_assignments: assign_classrooms(&best_calendar, constraints),
+    best_calendar,
+  }
+}
+
+#[derive(Default)]
+pub struct TabuSearchOptimizer {
+  custom_heuristics: Vec<WeightedHeuristic>,
+}
+
+impl TabuSearchOptimizer {
+  /// Adds a heuristic to the cost function, on top of the built-in ones. `weight` works the same
+  /// as in [`HeuristicWeights`].
+  pub fn register_heuristic(&mut self, heuristic: impl Heuristic + 'static, weight: u64) {
+    self
+      .custom_heuristics
+      .push(WeightedHeuristic::new(heuristic, weight));
+  }
+
+  pub fn run(
+    &self,
+    constraints: &OptimizationConstraints,
+    options: TabuSearchOptions,
+  ) -> TabuSearchOutput {
+    tabu_search(constraints, options, &self.custom_heuristics)
+  }
+}
+
+impl std::fmt::Debug for TabuSearchOptimizer {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("TabuSearchOptimizer")
+      .field(
+        "custom_heuristics",
+        &self
+          .custom_heuristics
+          .iter()
+          .map(|weighted_heuristic| weighted_heuristic.heuristic.name())
+          .collect_vec(),
+      )
+      .finish()
+  }
+}
+
+impl ClassCalendarOptimizer for TabuSearchOptimizer {
+  type OptimizerOptions = TabuSearchOptions;
+
+  fn generate_class_calendar(
+    &mut self,
+    constraints: OptimizationConstraints,
+    options: Self::OptimizerOptions,
+  ) -> ClassCalendar {
+    tabu_search(&constraints, options, &self.custom_heuristics).best_calendar
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::SchoolSchedule;
+
+  use super::*;
+
+  #[test]
+  fn test_tabu_search() {
+    let mut schedule = SchoolSchedule::default();
+    let classroom_type_key = schedule.add_new_classroom_type();
+    schedule.add_new_classroom(classroom_type_key);
+    let professor_key = schedule.add_new_professor();
+    for _ in 0..3 {
+      let class_key = schedule.add_new_class(professor_key);
+      let mut class_entry = schedule.get_class_entry(class_key).unwrap();
+      class_entry.set_allowed_classroom_types([classroom_type_key].into());
+      class_entry.set_hours(2);
+    }
+    let constraints = schedule.get_simulation_constraints();
+    let initial_state = schedule.class_calendar().clone();
+    let initial_cost = HeuristicWeights::default()
+      .cost_breakdown(&initial_state, constraints)
+      .iter()
+      .map(|heuristic_cost| heuristic_cost.cost)
+      .sum::<f64>();
+    assert!(!HardConstraintViolations::count(&initial_state, constraints).is_feasible());
+
+    let options = TabuSearchOptions {
+      initial_state,
+      iterations: 100,
+      seed: Some(0),
+      ..Default::default()
+    };
+    let output = tabu_search(constraints, options.clone(), &[]);
+    let final_cost = HeuristicWeights::default()
+      .cost_breakdown(&output.best_calendar, constraints)
+      .iter()
+      .map(|heuristic_cost| heuristic_cost.cost)
+      .sum::<f64>();
+    assert!(final_cost < initial_cost);
+    assert!((output.best_cost - final_cost).abs() < 1e-9);
+    assert!(output.best_hard_constraint_violations.is_feasible());
+    assert_eq!(output.best_calendar.class_entries().len(), 6);
+    assert_eq!(output.classroom_assignments.len(), 6);
+    assert_eq!(output.total_steps, 100);
+
+    // Same seed, same calendar
+    let repeated_output = tabu_search(constraints, options, &[]);
+    assert_eq!(
+      repeated_output.best_calendar.class_entries(),
+      output.best_calendar.class_entries()
+    );
+  }
+}
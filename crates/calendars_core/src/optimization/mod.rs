@@ -1,48 +1,61 @@
 mod class_calendar;
+mod hard_constraints;
 mod heuristics;
 mod methods;
 mod optimization_constraints;
+mod progress;
 mod stats_tracker;
 
 pub use class_calendar::ClassCalendar;
+pub use class_calendar::ClassEntryDelta;
 pub use class_calendar::SingleClassEntry;
+pub use hard_constraints::HardConstraintViolations;
+pub use heuristics::Heuristic;
+pub use heuristics::Violation;
 pub use optimization_constraints::AllowedClassroomTypes;
 pub use optimization_constraints::Availability;
 pub use optimization_constraints::Class;
 pub use optimization_constraints::ClassKey;
 pub use optimization_constraints::Classroom;
+pub use optimization_constraints::ClassroomKey;
 pub use optimization_constraints::ClassroomType;
+pub use optimization_constraints::ClassroomTypeKey;
 pub use optimization_constraints::Group;
 pub use optimization_constraints::OptimizationConstraints;
 pub use optimization_constraints::Professor;
 pub use optimization_constraints::ProfessorKey;
 pub use optimization_constraints::Semester;
+#[cfg(feature = "indicatif")]
+pub use progress::IndicatifProgress;
+pub use progress::Progress;
+pub use progress::ProgressObserver;
 
+pub use methods::exact_solver::ExactSolution;
+pub use methods::exact_solver::ExactSolver;
+pub use methods::exact_solver::ExactSolverError;
+pub use methods::exact_solver::ExactSolverOptions;
+pub use methods::greedy::greedy_class_calendar;
+pub use methods::simulated_annealing::AnnealingConfig;
+pub use methods::simulated_annealing::HeuristicCost;
+pub use methods::simulated_annealing::HeuristicWeights;
+pub use methods::simulated_annealing::InitialTemperature;
+pub use methods::simulated_annealing::MoveProbabilities;
+pub use methods::simulated_annealing::RunControl;
 pub use methods::simulated_annealing::SimulatedAnnealingOptimizer;
-
-#[deprecated]
-pub use methods::simulated_annealing::AdvancedSimulationOptions;
-#[deprecated]
-pub use methods::simulated_annealing::LiveUpdate;
-#[deprecated]
-pub use methods::simulated_annealing::ProgressOption;
-#[deprecated]
-pub use methods::simulated_annealing::SimulationOptions;
-#[deprecated]
 pub use methods::simulated_annealing::SimulationOutput;
-#[deprecated]
-pub use methods::simulated_annealing::TemperatureFunction;
-
 pub use methods::simulated_annealing::StopCondition;
-pub type CostFunction = fn(&ClassCalendar, &OptimizationConstraints) -> f64;
+pub use methods::simulated_annealing::TemperatureFunction;
+pub use methods::tabu_search::TabuSearchOptimizer;
+pub use methods::tabu_search::TabuSearchOptions;
+pub use methods::tabu_search::TabuSearchOutput;
 
 pub trait ClassCalendarOptimizer {
   type OptimizerOptions;
 
+  /// Custom costs are added with the `register_heuristic` of each optimizer.
   fn generate_class_calendar(
     &mut self,
     constraints: OptimizationConstraints,
     options: Self::OptimizerOptions,
-    cost_function: Option<CostFunction>,
   ) -> ClassCalendar;
 }
@@ -1,7 +1,7 @@
+use std::collections::BTreeSet;
 use std::fmt::Display;
 
 use crate::week_calendar::WeekCalendar;
-use enumflags2::{bitflags, BitFlags};
 use serde::{Deserialize, Serialize};
 use slotmap::{new_key_type, SlotMap};
 use strum::{EnumIter, VariantArray};
@@ -14,20 +14,48 @@ new_key_type! {
   pub struct ClassKey;
 }
 
+new_key_type! {
+  pub struct ClassroomKey;
+}
+
+new_key_type! {
+  pub struct ClassroomTypeKey;
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct OptimizationConstraints {
   pub classes: SlotMap<ClassKey, Class>,
   pub professors: SlotMap<ProfessorKey, Professor>,
+  #[serde(default)]
+  pub classrooms: SlotMap<ClassroomKey, Classroom>,
+  #[serde(default)]
+  pub classroom_types: SlotMap<ClassroomTypeKey, ClassroomType>,
+}
+
+impl OptimizationConstraints {
+  pub(crate) fn is_lab(&self, class: &Class) -> bool {
+    class
+      .allowed_classroom_types
+      .iter()
+      .filter_map(|classroom_type_key| self.classroom_types.get(*classroom_type_key))
+      .any(|classroom_type| classroom_type.lab)
+  }
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Class {
   pub professor_key: ProfessorKey,
+  #[serde(
+    deserialize_with = "crate::school_schedule::legacy::deserialize_allowed_classroom_types"
+  )]
   pub allowed_classroom_types: AllowedClassroomTypes,
   pub class_hours: u8,
   pub semester: Semester,
   pub group: Group,
   pub optative: bool,
+  /// Expected number of students. Only classrooms with enough capacity are assigned.
+  #[serde(default)]
+  pub enrollment: u32,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -157,89 +185,18 @@ pub enum Availability {
   NotAvailable,
 }
 
-pub type AllowedClassroomTypes = BitFlags<ClassroomType>;
-
-#[bitflags]
-#[repr(u8)]
-#[derive(
-  Serialize, Deserialize, Clone, Copy, EnumIter, VariantArray, PartialEq, Eq, Debug, PartialOrd, Ord,
-)]
-pub enum ClassroomType {
-  AulaSimple,
-  AulaDoble,
-  LabQuimica,
-  LabFisica,
-  AulaComputo,
-  NotAssigned,
-}
+pub type AllowedClassroomTypes = BTreeSet<ClassroomTypeKey>;
 
-impl Display for ClassroomType {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    f.write_str(match self {
-      ClassroomType::AulaSimple => "Aula Simple",
-      ClassroomType::AulaDoble => "Aula Doble",
-      ClassroomType::LabQuimica => "Lab Quimica",
-      ClassroomType::LabFisica => "Lab Fisica",
-      ClassroomType::AulaComputo => "Aula Computo",
-      ClassroomType::NotAssigned => "No asginado",
-    })
-  }
-}
-
-#[derive(
-  Serialize,
-  Deserialize,
-  Debug,
-  Clone,
-  Copy,
-  EnumIter,
-  VariantArray,
-  PartialEq,
-  Eq,
-  PartialOrd,
-  Ord,
-  Hash,
-)]
-pub enum Classroom {
-  Aula1,
-  Aula2_3,
-  Aula4,
-  Aula5_6,
-  SalaSeminarios,
-  SalaComputo,
-  LabFisica,
-  LabQuimica,
-  NotAssigned,
-}
-
-impl Display for Classroom {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    f.write_str(match self {
-      Classroom::Aula1 => "Aula 1",
-      Classroom::Aula2_3 => "Aula 2-3",
-      Classroom::Aula4 => "Aula 4",
-      Classroom::Aula5_6 => "Aula 5-6",
-      Classroom::SalaSeminarios => "Sala de Seminarios",
-      Classroom::SalaComputo => "Sala de Computo",
-      Classroom::LabFisica => "Lab de Fisica",
-      Classroom::LabQuimica => "Lab de Quimica",
-      Classroom::NotAssigned => "No asignado",
-    })
-  }
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ClassroomType {
+  /// Classes that use this type of classroom are considered labs.
+  pub lab: bool,
 }
 
-impl Classroom {
-  pub fn get_type(&self) -> ClassroomType {
-    match self {
-      Classroom::Aula1 => ClassroomType::AulaSimple,
-      Classroom::Aula2_3 => ClassroomType::AulaDoble,
-      Classroom::Aula4 => ClassroomType::AulaSimple,
-      Classroom::Aula5_6 => ClassroomType::AulaDoble,
-      Classroom::SalaSeminarios => ClassroomType::AulaDoble,
-      Classroom::SalaComputo => ClassroomType::AulaComputo,
-      Classroom::LabFisica => ClassroomType::LabFisica,
-      Classroom::LabQuimica => ClassroomType::LabQuimica,
-      Classroom::NotAssigned => ClassroomType::NotAssigned,
-    }
-  }
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Classroom {
+  pub classroom_type: ClassroomTypeKey,
+  /// `None` if the classroom has no capacity limit.
+  pub capacity: Option<u32>,
+  pub availability: WeekCalendar<Availability>,
 }
@@ -0,0 +1,247 @@
+use std::{
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+use crate::ClassCalendar;
+
+/// Snapshot of a running optimization, passed to a [`ProgressObserver`].
+#[derive(Debug, Clone)]
+pub struct Progress<'a> {
+  pub step: usize,
+  /// Number of steps of the run, if it has a step limit.
+  pub total_steps: Option<usize>,
+  /// Fraction of the run done, if its length is known.
+  pub fraction_done: Option<f64>,
+  pub elapsed: Duration,
+  /// Cost of the current state.
+  pub cost: f64,
+  /// `None` for methods without a temperature.
+  pub temperature: Option<f64>,
+  pub best_cost: f64,
+  pub best_hard_constraint_violations: u64,
+  pub best_calendar: &'a ClassCalendar,
+}
+
+/// Receives the progress of an optimization. It's called from the thread running it, so it
+/// should return quickly.
+pub trait ProgressObserver: Send + Sync {
+  fn on_progress(&self, progress: &Progress);
+
+  /// Called once when the run ends, stopped or not.
+  fn on_finish(&self, progress: &Progress) {
+    self.on_progress(progress);
+  }
+}
+
+impl<F: Fn(&Progress) + Send + Sync> ProgressObserver for F {
+  fn on_progress(&self, progress: &Progress) {
+    self(progress)
+  }
+}
+
+impl std::fmt::Debug for dyn ProgressObserver {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("ProgressObserver")
+  }
+}
+
+/// Latest report of one of the runs merged by [`CombinedProgress`].
+#[derive(Debug, Clone, Copy)]
+struct RunProgress {
+  step: usize,
+  total_steps: Option<usize>,
+  fraction_done: Option<f64>,
+  elapsed: Duration,
+  cost: f64,
+  temperature: Option<f64>,
+  finished: bool,
+}
+
+#[derive(Debug)]
+struct CombinedProgressState {
+  runs: Vec<Option<RunProgress>>,
+  /// Hard constraint violations, cost and calendar of the best calendar of any run.
+  best: Option<(u64, f64, ClassCalendar)>,
+}
+
+/// Merges the progress of runs made in parallel into a single report for `observer`: the steps
+/// of all of them, the fraction done of the one furthest from ending, the current state of the
+/// one with the lowest cost and the best calendar of any.
+#[derive(Debug)]
+pub(crate) struct CombinedProgress {
+  observer: Arc<dyn ProgressObserver>,
+  state: Mutex<CombinedProgressState>,
+}
+
+impl CombinedProgress {
+  pub(crate) fn new(observer: Arc<dyn ProgressObserver>, runs: usize) -> Arc<Self> {
+    Arc::new(Self {
+      observer,
+      state: Mutex::new(CombinedProgressState {
+        runs: vec![None; runs],
+        best: None,
+      }),
+    })
+  }
+
+  /// Observer for the run with index `run`.
+  pub(crate) fn run_observer(self: &Arc<Self>, run: usize) -> impl ProgressObserver {
+    RunObserver {
+      combined: self.clone(),
+      run,
+    }
+  }
+
+  fn report(&self, run: usize, progress: &Progress, finished: bool) {
+    let mut state = self.state.lock().unwrap();
+    state.runs[run] = Some(RunProgress {
+      step: progress.step,
+      total_steps: progress.total_steps,
+      fraction_done: progress.fraction_done,
+      elapsed: progress.elapsed,
+      cost: progress.cost,
+      temperature: progress.temperature,
+      finished,
+    });
+    let best = (progress.best_hard_constraint_violations, progress.best_cost);
+    if state
+      .best
+      .as_ref()
+      .is_none_or(|(hard, cost, _calendar)| best < (*hard, *cost))
+    {
+      state.best = Some((best.0, best.1, progress.best_calendar.clone()));
+    }
+
+    let runs = state.runs.iter().flatten().collect::<Vec<_>>();
+    let all_reported = runs.len() == state.runs.len();
+    let current = runs
+      .iter()
+      .min_by(|a, b| a.cost.total_cmp(&b.cost))
+      .unwrap();
+    let (best_hard_constraint_violations, best_cost, best_calendar) = state.best.as_ref().unwrap();
+    let combined = Progress {
+      step: runs.iter().map(|run| run.step).sum(),
+      total_steps: all_reported
+        .then(|| runs.iter().map(|run| run.total_steps).sum())
+        .flatten(),
+      // Runs that haven't reported yet haven't started
+      fraction_done: runs
+        .iter()
+        .map(|run| {
+          if run.finished {
+            Some(1.0)
+          } else {
+            run.fraction_done
+          }
+        })
+        .chain((!all_reported).then_some(Some(0.0)))
+        .try_fold(1.0_f64, |min, fraction_done| {
+          fraction_done.map(|fraction_done| min.min(fraction_done))
+        }),
+      elapsed: runs.iter().map(|run| run.elapsed).max().unwrap(),
+      cost: current.cost,
+      temperature: current.temperature,
+      best_cost: *best_cost,
+      best_hard_constraint_violations: *best_hard_constraint_violations,
+      best_calendar,
+    };
+    if all_reported && runs.iter().all(|run| run.finished) {
+      self.observer.on_finish(&combined);
+    } else {
+      self.observer.on_progress(&combined);
+    }
+  }
+}
+
+struct RunObserver {
+  combined: Arc<CombinedProgress>,
+  run: usize,
+}
+
+impl ProgressObserver for RunObserver {
+  fn on_progress(&self, progress: &Progress) {
+    self.combined.report(self.run, progress, false);
+  }
+
+  fn on_finish(&self, progress: &Progress) {
+    self.combined.report(self.run, progress, true);
+  }
+}
+
+/// Shows the progress of a run in an `indicatif` progress bar, with the best cost as message.
+/// Runs without a step limit show the fraction done in thousandths, or just count the steps if
+/// their length is unknown.
+#[cfg(feature = "indicatif")]
+#[derive(Debug, Clone)]
+pub struct IndicatifProgress {
+  progress_bar: indicatif::ProgressBar,
+}
+
+#[cfg(feature = "indicatif")]
+impl IndicatifProgress {
+  pub fn new(progress_bar: indicatif::ProgressBar) -> Self {
+    Self { progress_bar }
+  }
+}
+
+#[cfg(feature = "indicatif")]
+impl ProgressObserver for IndicatifProgress {
+  fn on_progress(&self, progress: &Progress) {
+    match (progress.total_steps, progress.fraction_done) {
+      (Some(total_steps), _) => {
+        self.progress_bar.set_length(total_steps as u64);
+        self.progress_bar.set_position(progress.step as u64);
+      }
+      (None, Some(fraction_done)) => {
+        self.progress_bar.set_length(1000);
+        self
+          .progress_bar
+          .set_position((fraction_done.clamp(0.0, 1.0) * 1000.0) as u64);
+      }
+      (None, None) => self.progress_bar.set_position(progress.step as u64),
+    }
+    self
+      .progress_bar
+      .set_message(format!("{:.3}", progress.best_cost));
+  }
+
+  fn on_finish(&self, progress: &Progress) {
+    self.on_progress(progress);
+    // Unlike `finish`, leaves a stopped run where it was
+    self.progress_bar.abandon();
+  }
+}
+
+#[cfg(all(test, feature = "indicatif"))]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_indicatif_progress() {
+    let progress_bar = indicatif::ProgressBar::hidden();
+    let observer = IndicatifProgress::new(progress_bar.clone());
+    let calendar = ClassCalendar::default();
+    let mut progress = Progress {
+      step: 30,
+      total_steps: Some(120),
+      fraction_done: Some(0.25),
+      elapsed: Duration::ZERO,
+      cost: 2.0,
+      temperature: Some(1.0),
+      best_cost: 1.5,
+      best_hard_constraint_violations: 0,
+      best_calendar: &calendar,
+    };
+    observer.on_progress(&progress);
+    assert_eq!(progress_bar.length(), Some(120));
+    assert_eq!(progress_bar.position(), 30);
+    assert_eq!(progress_bar.message(), "1.500");
+
+    progress.total_steps = None;
+    observer.on_finish(&progress);
+    assert_eq!(progress_bar.length(), Some(1000));
+    assert_eq!(progress_bar.position(), 250);
+    assert!(progress_bar.is_finished());
+  }
+}
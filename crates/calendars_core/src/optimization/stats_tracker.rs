@@ -51,10 +51,13 @@ impl StatsTracker {
     }
   }
 
+  /// Samples so that about `estimated_size` values are kept per stat. When the run has no known
+  /// length, it samples every `estimated_size` steps instead.
   pub fn with_estimated_size(total_runtime: &StopCondition, estimated_size: usize) -> Self {
-    let sampling_rate = match total_runtime {
-      StopCondition::Steps(steps) => SamplingRate::from(steps.div_ceil(estimated_size)),
-      StopCondition::Time(time) => SamplingRate::from(time.div_f64(estimated_size as f64)),
+    let sampling_rate = match (total_runtime.max_steps(), total_runtime.max_time()) {
+      (Some(steps), _) => SamplingRate::from(steps.div_ceil(estimated_size)),
+      (None, Some(time)) => SamplingRate::from(time.div_f64(estimated_size as f64)),
+      (None, None) => SamplingRate::from(estimated_size),
     };
     Self::new(sampling_rate)
   }
@@ -66,7 +69,7 @@ impl StatsTracker {
   pub fn inc_step(&mut self) {
     self.step_index += 1;
     if match self.sampling_rate {
-      SamplingRate::Steps(sampling_rate) => self.step_index % sampling_rate == 0,
+      SamplingRate::Steps(sampling_rate) => self.step_index.is_multiple_of(sampling_rate),
       SamplingRate::Duration(sampling_rate) => {
         self.latest_logging_step_start_instant.elapsed() > sampling_rate
       }
@@ -0,0 +1,95 @@
+//! Reading schedules saved before classrooms were configurable, when the classroom types and
+//! classrooms were fixed and classes stored their allowed classroom types as bitflags.
+
+use serde::{Deserialize, Deserializer};
+use slotmap::SlotMap;
+
+use super::SchoolSchedule;
+use crate::{AllowedClassroomTypes, ClassroomTypeKey};
+
+/// Name and `lab` flag of the old classroom types, in the order of their bits. The last bit,
+/// "not assigned", has no classroom type.
+const CLASSROOM_TYPES: [(&str, bool); 5] = [
+  ("Aula Simple", false),
+  ("Aula Doble", false),
+  ("Lab Quimica", true),
+  ("Lab Fisica", true),
+  ("Aula Computo", false),
+];
+
+/// Name of the old classrooms and the index of their type in [`CLASSROOM_TYPES`].
+const CLASSROOMS: [(&str, usize); 8] = [
+  ("Aula 1", 0),
+  ("Aula 2-3", 1),
+  ("Aula 4", 0),
+  ("Aula 5-6", 1),
+  ("Sala de Seminarios", 1),
+  ("Sala de Computo", 4),
+  ("Lab de Fisica", 3),
+  ("Lab de Quimica", 2),
+];
+
+/// Keys the old classroom types get when added to a schedule without classroom types.
+fn classroom_type_keys_by_bit() -> Vec<ClassroomTypeKey> {
+  let mut classroom_types = SlotMap::with_key();
+  CLASSROOM_TYPES
+    .iter()
+    .map(|_| classroom_types.insert(()))
+    .collect()
+}
+
+/// Reads the allowed classroom types of a class, either as keys or as the old bitflags.
+pub(crate) fn deserialize_allowed_classroom_types<'de, D: Deserializer<'de>>(
+  deserializer: D,
+) -> Result<AllowedClassroomTypes, D::Error> {
+  #[derive(Deserialize)]
+  #[serde(
+    untagged,
+    expecting = "a list of classroom type keys or classroom type bitflags"
+  )]
+  enum AllowedClassroomTypesFormat {
+    Keys(AllowedClassroomTypes),
+    Bitflags(u8),
+  }
+
+  Ok(
+    match AllowedClassroomTypesFormat::deserialize(deserializer)? {
+      AllowedClassroomTypesFormat::Keys(allowed_classroom_types) => allowed_classroom_types,
+      AllowedClassroomTypesFormat::Bitflags(bits) => classroom_type_keys_by_bit()
+        .into_iter()
+        .enumerate()
+        .filter(|(bit, _)| bits & (1 << bit) != 0)
+        .map(|(_, classroom_type_key)| classroom_type_key)
+        .collect(),
+    },
+  )
+}
+
+/// Adds the old classroom types and classrooms to a schedule read from an old file, so the keys
+/// its classes got from their bitflags point to them.
+pub(super) fn add_classrooms(schedule: &mut SchoolSchedule) {
+  assert!(schedule.simulation_constraints.classroom_types.is_empty());
+  let classroom_type_keys = CLASSROOM_TYPES
+    .iter()
+    .map(|(name, lab)| {
+      let classroom_type_key = schedule.add_new_classroom_type();
+      schedule
+        .get_classroom_type_metadata_mut(classroom_type_key)
+        .unwrap()
+        .name = name.to_string();
+      schedule
+        .get_classroom_type_mut(classroom_type_key)
+        .unwrap()
+        .lab = *lab;
+      classroom_type_key
+    })
+    .collect::<Vec<_>>();
+  debug_assert_eq!(classroom_type_keys, classroom_type_keys_by_bit());
+  for (name, classroom_type_index) in CLASSROOMS {
+    let classroom_key = schedule.add_new_classroom(classroom_type_keys[classroom_type_index]);
+    schedule
+      .get_classroom_metadata_mut(classroom_key)
+      .unwrap()
+      .name = name.to_string();
+  }
+}
@@ -2,13 +2,18 @@ use serde::{Deserialize, Serialize};
 use slotmap::SecondaryMap;
 
 use crate::ClassKey;
-
+use crate::ClassroomKey;
+use crate::ClassroomTypeKey;
 use crate::ProfessorKey;
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub(super) struct ScheduleMetadata {
   pub(super) professors: SecondaryMap<ProfessorKey, ProfessorMetadata>,
   pub(super) classes: SecondaryMap<ClassKey, ClassMetadata>,
+  #[serde(default)]
+  pub(super) classrooms: SecondaryMap<ClassroomKey, ClassroomMetadata>,
+  #[serde(default)]
+  pub(super) classroom_types: SecondaryMap<ClassroomTypeKey, ClassroomTypeMetadata>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -22,3 +27,13 @@ pub struct ClassMetadata {
   pub rgba: [u8; 4],
   pub class_code: String,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClassroomMetadata {
+  pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClassroomTypeMetadata {
+  pub name: String,
+}
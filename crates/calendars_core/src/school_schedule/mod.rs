@@ -4,16 +4,22 @@ use chrono::{Datelike, Days, TimeZone, Timelike, Utc};
 use slotmap::SecondaryMap;
 
 use crate::{
-  week_calendar, AllowedClassroomTypes, Class, ClassCalendar, ClassKey, Classroom, Day, Group,
-  OptimizationConstraints, Professor, ProfessorKey, Semester, SingleClassEntry, Timeslot,
+  classroom_assignment::assign_classrooms, greedy_class_calendar, week_calendar,
+  AllowedClassroomTypes, Availability, Class, ClassCalendar, ClassKey, Classroom, ClassroomKey,
+  ClassroomType, ClassroomTypeKey, Day, Group, HardConstraintViolations, HeuristicCost,
+  HeuristicWeights, OptimizationConstraints, Professor, ProfessorKey, Semester, Timeslot,
+  Violation,
 };
 use icalendar::{Component, EventLike};
+pub(crate) mod legacy;
 mod metadata_types;
-use metadata_types::{ClassMetadata, ProfessorMetadata, ScheduleMetadata};
+use metadata_types::{
+  ClassMetadata, ClassroomMetadata, ClassroomTypeMetadata, ProfessorMetadata, ScheduleMetadata,
+};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::week_calendar::WeekCalendar;
+use crate::week_calendar::{WeekCalendar, WeekLayout};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
 pub enum ClassFilter {
@@ -21,13 +27,28 @@ pub enum ClassFilter {
   Any,
   None,
   Professor(ProfessorKey),
-  Classroom(Classroom),
+  Classroom(ClassroomKey),
   Semester(Semester),
 }
 
 #[derive(thiserror::Error, Debug)]
-#[error("Class hours in calendars do not match.")]
-pub struct ClassHourCountNotMatchingError {}
+pub enum ReplaceClassCalendarError {
+  #[error("Class hours in calendars do not match.")]
+  ClassHourCountNotMatching,
+  #[error("Week layouts of the calendars do not match.")]
+  WeekLayoutNotMatching,
+}
+
+/// Calendars of a schedule that don't have the days and timeslots of its week layout.
+#[derive(thiserror::Error, Debug)]
+enum WeekLayoutMismatchError {
+  #[error("The class calendar doesn't fit the week layout")]
+  ClassCalendar,
+  #[error("The availability of professor {0:?} doesn't fit the week layout")]
+  ProfessorAvailability(ProfessorKey),
+  #[error("The availability of classroom {0:?} doesn't fit the week layout")]
+  ClassroomAvailability(ClassroomKey),
+}
 
 #[derive(Debug)]
 pub struct ClassEntry<'a> {
@@ -45,6 +66,8 @@ impl<'a, 'b> ClassEntry<'a> {
       .unwrap()
   }
 
+  /// New hours start at the first timeslot of the first day. [`greedy_class_calendar`] spreads
+  /// them out.
   pub fn set_hours(&mut self, class_hours: u8) {
     let class = self
       .school_schedule
@@ -82,6 +105,9 @@ impl<'a, 'b> ClassEntry<'a> {
         class.class_hours = class_hours;
       }
     };
+    if class_hours != curr_class_hours {
+      self.school_schedule.update_classroom_assignments();
+    }
   }
 
   pub fn set_professor_id(&mut self, professor_key: ProfessorKey) {
@@ -101,13 +127,24 @@ impl<'a, 'b> ClassEntry<'a> {
 
   pub fn set_allowed_classroom_types(&mut self, allowed_classroom_types: AllowedClassroomTypes) {
     let class = self.get_class();
-    class.allowed_classroom_types = allowed_classroom_types;
+    if class.allowed_classroom_types != allowed_classroom_types {
+      class.allowed_classroom_types = allowed_classroom_types;
+      self.school_schedule.update_classroom_assignments();
+    }
   }
 
   pub fn set_optative(&mut self, optative: bool) {
     let class = self.get_class();
     class.optative = optative;
   }
+
+  pub fn set_enrollment(&mut self, enrollment: u32) {
+    let class = self.get_class();
+    if class.enrollment != enrollment {
+      class.enrollment = enrollment;
+      self.school_schedule.update_classroom_assignments();
+    }
+  }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -115,17 +152,140 @@ pub struct ClassroomAssignmentKey {
   pub day: week_calendar::Day,
   pub timeslot: week_calendar::Timeslot,
   pub class_key: ClassKey,
+  /// Which of the hours of the class in the timeslot, when it has more than one there.
+  #[serde(default)]
+  pub index: u8,
+}
+
+/// JSON maps can only have string keys, so classroom assignments are stored as a list of pairs.
+pub(crate) mod classroom_assignments_serde {
+  use std::collections::BTreeMap;
+
+  use serde::{de::IgnoredAny, Deserialize, Deserializer, Serializer};
+
+  use super::ClassroomAssignmentKey;
+  use crate::ClassroomKey;
+
+  pub(crate) fn serialize<S: Serializer>(
+    classroom_assignments: &BTreeMap<ClassroomAssignmentKey, ClassroomKey>,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(classroom_assignments.iter())
+  }
+
+  pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<BTreeMap<ClassroomAssignmentKey, ClassroomKey>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged, expecting = "a list of classroom assignments")]
+    enum ClassroomAssignmentsFormat {
+      Pairs(Vec<(ClassroomAssignmentKey, ClassroomKey)>),
+      /// Old files stored them as a map, which could only be saved while empty.
+      EmptyMap(BTreeMap<String, IgnoredAny>),
+    }
+
+    Ok(
+      match ClassroomAssignmentsFormat::deserialize(deserializer)? {
+        ClassroomAssignmentsFormat::Pairs(classroom_assignments) => {
+          classroom_assignments.into_iter().collect()
+        }
+        ClassroomAssignmentsFormat::EmptyMap(classroom_assignments) => {
+          debug_assert!(classroom_assignments.is_empty());
+          BTreeMap::new()
+        }
+      },
+    )
+  }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+/// Version of the file format of [`SchoolSchedule`]. Files without one are from before classrooms
+/// were configurable.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(remote = "Self")]
 pub struct SchoolSchedule {
+  #[serde(default)]
+  format_version: u32,
   metadata: ScheduleMetadata,
   simulation_constraints: OptimizationConstraints,
   class_calendar: ClassCalendar,
-  classroom_assignments: BTreeMap<ClassroomAssignmentKey, Classroom>,
+  #[serde(default, with = "classroom_assignments_serde")]
+  classroom_assignments: BTreeMap<ClassroomAssignmentKey, ClassroomKey>,
+  #[serde(default)]
+  heuristic_weights: HeuristicWeights,
+}
+
+impl Default for SchoolSchedule {
+  fn default() -> Self {
+    Self {
+      format_version: FORMAT_VERSION,
+      metadata: Default::default(),
+      simulation_constraints: Default::default(),
+      class_calendar: Default::default(),
+      classroom_assignments: Default::default(),
+      heuristic_weights: Default::default(),
+    }
+  }
+}
+
+impl Serialize for SchoolSchedule {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    SchoolSchedule::serialize(self, serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for SchoolSchedule {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let mut school_schedule = SchoolSchedule::deserialize(deserializer)?;
+    if school_schedule.format_version == 0 {
+      // Files saved while classrooms were being made configurable have no version either
+      if school_schedule
+        .simulation_constraints
+        .classroom_types
+        .is_empty()
+      {
+        legacy::add_classrooms(&mut school_schedule);
+      }
+      school_schedule.format_version = FORMAT_VERSION;
+    }
+    school_schedule
+      .check_week_layout()
+      .map_err(serde::de::Error::custom)?;
+    Ok(school_schedule)
+  }
 }
 
 impl SchoolSchedule {
+  /// Checks that the calendars read from a file have the days and timeslots of the schedule.
+  fn check_week_layout(&self) -> Result<(), WeekLayoutMismatchError> {
+    let week_layout = self.week_layout();
+    if !self.class_calendar.fits_week_layout() {
+      return Err(WeekLayoutMismatchError::ClassCalendar);
+    }
+    if let Some((professor_key, _professor)) = self
+      .simulation_constraints
+      .professors
+      .iter()
+      .find(|(_k, professor)| !professor.availability.fits(week_layout))
+    {
+      return Err(WeekLayoutMismatchError::ProfessorAvailability(
+        professor_key,
+      ));
+    }
+    if let Some((classroom_key, _classroom)) = self
+      .simulation_constraints
+      .classrooms
+      .iter()
+      .find(|(_k, classroom)| !classroom.availability.fits(week_layout))
+    {
+      return Err(WeekLayoutMismatchError::ClassroomAvailability(
+        classroom_key,
+      ));
+    }
+    Ok(())
+  }
+
   pub fn get_simulation_constraints(&self) -> &OptimizationConstraints {
     &self.simulation_constraints
   }
@@ -134,7 +294,7 @@ impl SchoolSchedule {
     self.simulation_constraints.classes.get(class_key)
   }
 
-  pub fn get_class_entry(&mut self, class_key: ClassKey) -> Option<ClassEntry> {
+  pub fn get_class_entry(&mut self, class_key: ClassKey) -> Option<ClassEntry<'_>> {
     Some(ClassEntry {
       school_schedule: self,
       class_key,
@@ -183,11 +343,46 @@ impl SchoolSchedule {
     self.simulation_constraints.professors.len()
   }
 
+  pub fn heuristic_weights(&self) -> &HeuristicWeights {
+    &self.heuristic_weights
+  }
+
+  pub fn heuristic_weights_mut(&mut self) -> &mut HeuristicWeights {
+    &mut self.heuristic_weights
+  }
+
+  pub fn week_layout(&self) -> &WeekLayout {
+    self.class_calendar.week_layout()
+  }
+
+  /// Changes the shape of the week. Professor availabilities and classes are kept in the days and
+  /// timeslots that exist in both layouts, classes outside the new layout are moved to the nearest
+  /// valid timeslot.
+  pub fn set_week_layout(&mut self, week_layout: WeekLayout) {
+    let old_week_layout = self.week_layout().clone();
+    if old_week_layout == week_layout {
+      return;
+    }
+    for professor in self.simulation_constraints.professors.values_mut() {
+      professor.availability = professor
+        .availability
+        .with_week_layout(&old_week_layout, &week_layout);
+    }
+    for classroom in self.simulation_constraints.classrooms.values_mut() {
+      classroom.availability = classroom
+        .availability
+        .with_week_layout(&old_week_layout, &week_layout);
+    }
+    self.class_calendar = self.class_calendar.with_week_layout(week_layout);
+    self.update_classroom_assignments();
+  }
+
   pub fn add_new_professor(&mut self) -> ProfessorKey {
+    let week_layout = self.week_layout().clone();
     let professor_metadata = &mut self.metadata.professors;
     let professors = &mut self.simulation_constraints.professors;
     let professor_key = professors.insert(Professor {
-      availability: WeekCalendar::default(),
+      availability: WeekCalendar::new(&week_layout),
       priority: 0.0,
     });
     professor_metadata.insert(
@@ -199,6 +394,84 @@ impl SchoolSchedule {
     professor_key
   }
 
+  pub fn get_classroom(&self, classroom_key: ClassroomKey) -> Option<&Classroom> {
+    self.simulation_constraints.classrooms.get(classroom_key)
+  }
+
+  /// Call [`Self::update_classroom_assignments`] after changing the classroom.
+  pub fn get_classroom_mut(&mut self, classroom_key: ClassroomKey) -> Option<&mut Classroom> {
+    self
+      .simulation_constraints
+      .classrooms
+      .get_mut(classroom_key)
+  }
+
+  pub fn get_classroom_metadata(&self, classroom_key: ClassroomKey) -> Option<&ClassroomMetadata> {
+    self.metadata.classrooms.get(classroom_key)
+  }
+
+  pub fn get_classroom_metadata_mut(
+    &mut self,
+    classroom_key: ClassroomKey,
+  ) -> Option<&mut ClassroomMetadata> {
+    self.metadata.classrooms.get_mut(classroom_key)
+  }
+
+  pub fn get_classroom_type_mut(
+    &mut self,
+    classroom_type_key: ClassroomTypeKey,
+  ) -> Option<&mut ClassroomType> {
+    self
+      .simulation_constraints
+      .classroom_types
+      .get_mut(classroom_type_key)
+  }
+
+  pub fn get_classroom_type_metadata(
+    &self,
+    classroom_type_key: ClassroomTypeKey,
+  ) -> Option<&ClassroomTypeMetadata> {
+    self.metadata.classroom_types.get(classroom_type_key)
+  }
+
+  pub fn get_classroom_type_metadata_mut(
+    &mut self,
+    classroom_type_key: ClassroomTypeKey,
+  ) -> Option<&mut ClassroomTypeMetadata> {
+    self.metadata.classroom_types.get_mut(classroom_type_key)
+  }
+
+  pub fn add_new_classroom_type(&mut self) -> ClassroomTypeKey {
+    let classroom_type_key = self
+      .simulation_constraints
+      .classroom_types
+      .insert(Default::default());
+    self.metadata.classroom_types.insert(
+      classroom_type_key,
+      ClassroomTypeMetadata {
+        name: "Nuevo Tipo de Aula".to_string(),
+      },
+    );
+    classroom_type_key
+  }
+
+  pub fn add_new_classroom(&mut self, classroom_type_key: ClassroomTypeKey) -> ClassroomKey {
+    let availability = WeekCalendar::filled(self.week_layout(), Availability::Available);
+    let classroom_key = self.simulation_constraints.classrooms.insert(Classroom {
+      classroom_type: classroom_type_key,
+      capacity: None,
+      availability,
+    });
+    self.metadata.classrooms.insert(
+      classroom_key,
+      ClassroomMetadata {
+        name: "Nueva Aula".to_string(),
+      },
+    );
+    self.update_classroom_assignments();
+    classroom_key
+  }
+
   pub fn add_new_class(&mut self, professor_key: ProfessorKey) -> ClassKey {
     let class_key = self
       .simulation_constraints
@@ -232,26 +505,100 @@ impl SchoolSchedule {
   pub fn replace_class_calendar(
     &mut self,
     class_calendar: ClassCalendar,
-  ) -> Result<(), ClassHourCountNotMatchingError> {
+  ) -> Result<(), ReplaceClassCalendarError> {
+    if self.week_layout() != class_calendar.week_layout() {
+      return Err(ReplaceClassCalendarError::WeekLayoutNotMatching);
+    }
     let current_class_hour_count = count_class_hours(&self.class_calendar);
     let class_hour_count = count_class_hours(&class_calendar);
     if current_class_hour_count != class_hour_count {
-      return Err(ClassHourCountNotMatchingError {});
+      return Err(ReplaceClassCalendarError::ClassHourCountNotMatching);
     }
     self.class_calendar = class_calendar;
+    self.update_classroom_assignments();
     Ok(())
   }
 
-  pub fn filter_class(&self, class_entry: &SingleClassEntry, class_filter: &ClassFilter) -> bool {
+  /// Replaces the schedule with a greedy one that avoids conflicts where it can. Locked class
+  /// entries stay in place. A better starting point for the optimizer than the default.
+  pub fn generate_initial_class_calendar(&mut self) {
+    let class_calendar = greedy_class_calendar(&self.simulation_constraints, &self.class_calendar);
+    self.replace_class_calendar(class_calendar).unwrap();
+  }
+
+  /// Locked class entries are kept in place by the optimizer.
+  pub fn set_locked(&mut self, day: Day, timeslot: Timeslot, class_key: ClassKey, locked: bool) {
+    self
+      .class_calendar
+      .set_locked(day, timeslot, class_key, locked);
+  }
+
+  pub fn classroom_assignments(&self) -> &BTreeMap<ClassroomAssignmentKey, ClassroomKey> {
+    &self.classroom_assignments
+  }
+
+  pub fn get_classroom_assignment(&self, key: &ClassroomAssignmentKey) -> Option<ClassroomKey> {
+    self.classroom_assignments.get(key).copied()
+  }
+
+  /// Key of every class entry in [`Self::classroom_assignments`], whether it got a classroom or
+  /// not. Repeated entries of a class in a timeslot get increasing indices.
+  pub fn classroom_assignment_keys(&self) -> impl Iterator<Item = ClassroomAssignmentKey> + '_ {
+    let mut counts: BTreeMap<(Day, Timeslot, ClassKey), u8> = BTreeMap::new();
+    self
+      .class_calendar
+      .class_entries()
+      .iter()
+      .map(move |class_entry| {
+        let count = counts
+          .entry((class_entry.day, class_entry.timeslot, class_entry.class_key))
+          .or_default();
+        let key = ClassroomAssignmentKey {
+          day: class_entry.day,
+          timeslot: class_entry.timeslot,
+          class_key: class_entry.class_key,
+          index: *count,
+        };
+        *count += 1;
+        key
+      })
+  }
+
+  pub fn hard_constraint_violations(&self) -> HardConstraintViolations {
+    HardConstraintViolations::count(&self.class_calendar, &self.simulation_constraints)
+  }
+
+  /// Cost of the current schedule per heuristic, using the schedule's heuristic weights.
+  pub fn cost_breakdown(&self) -> Vec<HeuristicCost> {
+    self
+      .heuristic_weights
+      .cost_breakdown(&self.class_calendar, &self.simulation_constraints)
+  }
+
+  /// Broken rules in the current schedule, for every heuristic with a non-zero weight.
+  pub fn violations(&self) -> Vec<Violation> {
+    self
+      .heuristic_weights
+      .violations(&self.class_calendar, &self.simulation_constraints)
+  }
+
+  /// Recomputes the classroom of every class entry. The class setters and the calendar changes
+  /// call it, but edits through [`Self::get_classroom_mut`] need it too.
+  pub fn update_classroom_assignments(&mut self) {
+    self.classroom_assignments =
+      assign_classrooms(&self.class_calendar, &self.simulation_constraints);
+  }
+
+  pub fn filter_class(&self, key: &ClassroomAssignmentKey, class_filter: &ClassFilter) -> bool {
     match class_filter {
       ClassFilter::Professor(professor_key) => {
-        *professor_key == self.get_class(class_entry.class_key).unwrap().professor_key
+        *professor_key == self.get_class(key.class_key).unwrap().professor_key
       }
-      ClassFilter::Classroom(classroom) => {
-        todo!()
+      ClassFilter::Classroom(classroom_key) => {
+        self.get_classroom_assignment(key) == Some(*classroom_key)
       }
       ClassFilter::Semester(semester) => {
-        *semester == self.get_class(class_entry.class_key).unwrap().semester
+        *semester == self.get_class(key.class_key).unwrap().semester
       }
       ClassFilter::Any => true,
       ClassFilter::None => false,
@@ -275,56 +622,67 @@ impl SchoolSchedule {
     let mut cal = icalendar::Calendar::new();
     struct ClassRange {
       class_key: ClassKey,
+      classroom_key: Option<ClassroomKey>,
       day: week_calendar::Day,
       start_timeslot: week_calendar::Timeslot,
       /// inclusive
       end_timeslot: week_calendar::Timeslot,
     }
     let mut class_ranges: Vec<ClassRange> = Vec::new();
-    for class_entry in self
-      .class_calendar
-      .class_entries()
-      .iter()
-      .filter(|class_entry| self.filter_class(class_entry, class_filter))
+    for key in self
+      .classroom_assignment_keys()
+      .filter(|key| self.filter_class(key, class_filter))
     {
       let new_range = ClassRange {
-        class_key: class_entry.class_key,
-        day: class_entry.day,
-        start_timeslot: class_entry.timeslot,
-        end_timeslot: class_entry.timeslot,
+        class_key: key.class_key,
+        classroom_key: self.get_classroom_assignment(&key),
+        day: key.day,
+        start_timeslot: key.timeslot,
+        end_timeslot: key.timeslot,
       };
       if let Some(prev_range) = class_ranges.iter_mut().find(|r| {
         r.class_key == new_range.class_key
+          && r.classroom_key == new_range.classroom_key
           && r.day == new_range.day
-          && usize::from(r.end_timeslot).checked_add(1_usize).map_or(
-            false,
-            |prev_range_end_timeslot_plus_one| {
+          && usize::from(r.end_timeslot)
+            .checked_add(1_usize)
+            .is_some_and(|prev_range_end_timeslot_plus_one| {
               prev_range_end_timeslot_plus_one == usize::from(new_range.start_timeslot)
-            },
-          )
+            })
       }) {
         prev_range.end_timeslot = new_range.end_timeslot;
       } else {
         class_ranges.push(new_range);
       }
     }
+    let week_layout = self.week_layout();
     for class_range in class_ranges {
       let mut event = icalendar::Event::new();
-      let start_time = semester_start
+      let day_start = semester_start
         .checked_add_days(Days::new(usize::from(class_range.day) as u64))
-        .unwrap()
-        .with_hour(8 + u32::try_from(usize::from(class_range.start_timeslot)).unwrap())
+        .unwrap();
+      let start = week_layout.timeslot_start(class_range.start_timeslot);
+      let start_time = day_start
+        .with_hour(start.hour())
+        .and_then(|t| t.with_minute(start.minute()))
         .unwrap()
         .with_timezone(&Utc);
-      let end_time = semester_start
-        .checked_add_days(Days::new(usize::from(class_range.day) as u64))
-        .unwrap()
-        .with_hour(8 + u32::try_from(usize::from(class_range.end_timeslot)).unwrap()) // +1 because end_timeslot is inclusive
+      // end_timeslot is inclusive, so the event ends when it does
+      let end = week_layout.timeslot_end(class_range.end_timeslot);
+      let end_time = day_start
+        .with_hour(end.hour())
+        .and_then(|t| t.with_minute(end.minute()))
         .unwrap()
         .with_timezone(&Utc);
       event.starts(start_time);
       event.ends(end_time);
       event.summary(&self.get_class_metadata(class_range.class_key).unwrap().name);
+      if let Some(classroom_metadata) = class_range
+        .classroom_key
+        .and_then(|classroom_key| self.get_classroom_metadata(classroom_key))
+      {
+        event.location(&classroom_metadata.name);
+      }
 
       cal.push(event);
     }
@@ -336,8 +694,8 @@ fn count_class_hours(class_calendar: &ClassCalendar) -> SecondaryMap<ClassKey, u
   let mut class_hour_count = SecondaryMap::new();
   for class_key in class_calendar.iter_class_keys() {
     let mut sum: u32 = 0;
-    for day in week_calendar::Day::all() {
-      for timeslot in week_calendar::Timeslot::all() {
+    for day in class_calendar.week_layout().days() {
+      for timeslot in class_calendar.week_layout().timeslots() {
         sum += class_calendar.get_count(day, timeslot, class_key) as u32;
       }
     }
@@ -348,6 +706,8 @@ fn count_class_hours(class_calendar: &ClassCalendar) -> SecondaryMap<ClassKey, u
 
 #[cfg(test)]
 mod test {
+  use itertools::Itertools;
+
   use super::*;
 
   #[test]
@@ -370,4 +730,268 @@ mod test {
     );
     assert_eq!(school_schedule.class_calendar.class_entries().len(), 10);
   }
+
+  #[test]
+  fn test_set_week_layout() {
+    let mut school_schedule = SchoolSchedule::default();
+    let professor_key = school_schedule.add_new_professor();
+    let class_key = school_schedule.add_new_class(professor_key);
+    school_schedule
+      .get_class_entry(class_key)
+      .unwrap()
+      .set_hours(3);
+    let week_layout = WeekLayout::hourly(6, 7, 14).unwrap();
+    school_schedule.set_week_layout(week_layout.clone());
+    assert_eq!(*school_schedule.week_layout(), week_layout);
+    assert_eq!(*school_schedule.class_calendar().week_layout(), week_layout);
+    assert_eq!(school_schedule.class_calendar.class_entries().len(), 3);
+    let professor_key = school_schedule.add_new_professor();
+    let saturday_20_00 = school_schedule
+      .get_professor_mut(professor_key)
+      .unwrap()
+      .availability
+      .get(crate::DAY_SATURDAY, Timeslot::from_usize(13).unwrap());
+    assert_eq!(*saturday_20_00, Default::default());
+  }
+
+  #[test]
+  fn test_deserialize_week_layout_mismatch() {
+    let mut school_schedule = SchoolSchedule::default();
+    let professor_key = school_schedule.add_new_professor();
+    let class_key = school_schedule.add_new_class(professor_key);
+    school_schedule
+      .get_class_entry(class_key)
+      .unwrap()
+      .set_hours(3);
+    let mut json = serde_json::to_value(&school_schedule).unwrap();
+    assert!(serde_json::from_value::<SchoolSchedule>(json.clone()).is_ok());
+
+    // A six day layout with the calendars of a five day one
+    json["class_calendar"]["week_layout"] =
+      serde_json::to_value(WeekLayout::hourly(6, 7, 12).unwrap()).unwrap();
+    assert!(serde_json::from_value::<SchoolSchedule>(json.clone()).is_err());
+
+    // The class calendar fits, but the availability of the professor doesn't
+    school_schedule.set_week_layout(WeekLayout::hourly(6, 7, 12).unwrap());
+    let mut json = serde_json::to_value(&school_schedule).unwrap();
+    let mut five_day_schedule = SchoolSchedule::default();
+    five_day_schedule.add_new_professor();
+    json["simulation_constraints"]["professors"] =
+      serde_json::to_value(&five_day_schedule.simulation_constraints.professors).unwrap();
+    let error = serde_json::from_value::<SchoolSchedule>(json)
+      .unwrap_err()
+      .to_string();
+    assert!(error.contains("availability of professor"), "{error}");
+  }
+
+  #[test]
+  fn test_export_ics_periodic_week_layout() {
+    let mut school_schedule = SchoolSchedule::default();
+    school_schedule.set_week_layout(
+      WeekLayout::periodic(
+        5,
+        chrono::NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+        chrono::Duration::minutes(50),
+        chrono::Duration::minutes(10),
+        14,
+      )
+      .unwrap(),
+    );
+    let professor_key = school_schedule.add_new_professor();
+    let class_key = school_schedule.add_new_class(professor_key);
+    school_schedule
+      .get_class_entry(class_key)
+      .unwrap()
+      .set_hours(2);
+    let mut class_calendar = school_schedule.class_calendar().clone();
+    let t0 = Timeslot::from_usize(0).unwrap();
+    let t1 = Timeslot::from_usize(1).unwrap();
+    class_calendar.move_one_class(crate::DAY_MONDAY, t0, crate::DAY_MONDAY, t1, class_key);
+    school_schedule
+      .replace_class_calendar(class_calendar)
+      .unwrap();
+    let ics = school_schedule.export_ics(&ClassFilter::Any).to_string();
+    // Dublin is UTC+1 in August
+    assert!(ics.contains("DTSTART:20220808T060000Z"));
+    assert!(ics.contains("DTEND:20220808T075000Z"));
+  }
+
+  #[test]
+  fn test_classroom_assignments() {
+    let mut school_schedule = SchoolSchedule::default();
+    let classroom_type_key = school_schedule.add_new_classroom_type();
+    let classroom_key = school_schedule.add_new_classroom(classroom_type_key);
+    school_schedule
+      .get_classroom_metadata_mut(classroom_key)
+      .unwrap()
+      .name = "Aula 1".to_string();
+    let professor_key = school_schedule.add_new_professor();
+    let class_key = school_schedule.add_new_class(professor_key);
+    let mut class_entry = school_schedule.get_class_entry(class_key).unwrap();
+    class_entry.set_allowed_classroom_types([classroom_type_key].into());
+    class_entry.set_hours(1);
+    let other_class_key = school_schedule.add_new_class(professor_key);
+    school_schedule
+      .get_class_entry(other_class_key)
+      .unwrap()
+      .set_hours(1);
+    school_schedule
+      .replace_class_calendar(school_schedule.class_calendar().clone())
+      .unwrap();
+    assert_eq!(school_schedule.classroom_assignments().len(), 1);
+
+    let class_filter = ClassFilter::Classroom(classroom_key);
+    let filtered_class_keys = school_schedule
+      .classroom_assignment_keys()
+      .filter(|key| school_schedule.filter_class(key, &class_filter))
+      .map(|key| key.class_key)
+      .collect::<Vec<_>>();
+    assert_eq!(filtered_class_keys, vec![class_key]);
+    let ics = school_schedule.export_ics(&class_filter).to_string();
+    assert!(ics.contains("LOCATION:Aula 1"));
+
+    let json = serde_json::to_string(&school_schedule).unwrap();
+    let school_schedule: SchoolSchedule = serde_json::from_str(&json).unwrap();
+    assert_eq!(school_schedule.classroom_assignments().len(), 1);
+  }
+
+  #[test]
+  fn test_classroom_assignments_follow_class_edits() {
+    let mut school_schedule = SchoolSchedule::default();
+    let classroom_type_key = school_schedule.add_new_classroom_type();
+    let professor_key = school_schedule.add_new_professor();
+    let class_key = school_schedule.add_new_class(professor_key);
+    let mut class_entry = school_schedule.get_class_entry(class_key).unwrap();
+    class_entry.set_allowed_classroom_types([classroom_type_key].into());
+    class_entry.set_hours(2);
+    assert!(school_schedule.classroom_assignments().is_empty());
+
+    school_schedule.add_new_classroom(classroom_type_key);
+    school_schedule.add_new_classroom(classroom_type_key);
+    school_schedule
+      .get_class_entry(class_key)
+      .unwrap()
+      .set_hours(3);
+    let has_classroom = |school_schedule: &SchoolSchedule| {
+      school_schedule
+        .classroom_assignment_keys()
+        .map(|key| school_schedule.get_classroom_assignment(&key).is_some())
+        .collect_vec()
+    };
+    // The three hours share a timeslot and there are two classrooms
+    assert_eq!(has_classroom(&school_schedule), vec![true, true, false]);
+
+    school_schedule
+      .get_class_entry(class_key)
+      .unwrap()
+      .set_hours(2);
+    assert_eq!(has_classroom(&school_schedule), vec![true, true]);
+
+    school_schedule
+      .get_class_entry(class_key)
+      .unwrap()
+      .set_enrollment(10);
+    for classroom_key in school_schedule
+      .get_simulation_constraints()
+      .classrooms
+      .keys()
+      .collect_vec()
+    {
+      school_schedule
+        .get_classroom_mut(classroom_key)
+        .unwrap()
+        .capacity = Some(20);
+    }
+    school_schedule
+      .get_class_entry(class_key)
+      .unwrap()
+      .set_enrollment(30);
+    assert_eq!(has_classroom(&school_schedule), vec![false, false]);
+  }
+
+  #[test]
+  fn test_legacy_format() {
+    // Schedule saved before classrooms were configurable, with one class allowed in
+    // "Aula Simple" or "Aula Doble" and another in "Lab Fisica"
+    let vacant = serde_json::json!({ "value": null, "version": 0 });
+    let occupied = |value| serde_json::json!({ "value": value, "version": 1 });
+    let class = |allowed_classroom_types: u8, class_hours: u8| {
+      occupied(serde_json::json!({
+        "professor_key": { "idx": 1, "version": 1 },
+        "allowed_classroom_types": allowed_classroom_types,
+        "class_hours": class_hours,
+        "semester": "S1",
+        "group": "G1",
+        "optative": false,
+      }))
+    };
+    let class_metadata = occupied(serde_json::json!({
+      "name": "New Class",
+      "rgba": [255, 255, 224, 255],
+      "class_code": "0000",
+    }));
+    let class_calendar = |class_hours: u8| {
+      let mut data = vec![0; 60];
+      data[0] = class_hours;
+      occupied(serde_json::json!({ "data": data }))
+    };
+    let class_entry = |idx: u32| serde_json::json!({ "day": 0, "timeslot": 0, "class_key": { "idx": idx, "version": 1 } });
+    let json = serde_json::json!({
+      "metadata": {
+        "professors": [vacant, occupied(serde_json::json!({ "name": "Nuevo Profesor" }))],
+        "classes": [vacant, class_metadata, class_metadata],
+      },
+      "simulation_constraints": {
+        "classes": [vacant, class(3, 2), class(8, 1)],
+        "professors": [vacant, occupied(serde_json::json!({
+          "availability": { "data": vec!["AvailableIfNeeded"; 60] },
+          "priority": 0.0,
+        }))],
+      },
+      "class_calendar": {
+        "data": [vacant, class_calendar(2), class_calendar(1)],
+        "class_entries": [class_entry(1), class_entry(1), class_entry(2)],
+      },
+      "classroom_assignments": {},
+    });
+    let school_schedule: SchoolSchedule = serde_json::from_value(json).unwrap();
+
+    let constraints = school_schedule.get_simulation_constraints();
+    assert_eq!(constraints.classroom_types.len(), 5);
+    assert_eq!(constraints.classrooms.len(), 8);
+    let allowed_classroom_types = constraints
+      .classes
+      .values()
+      .map(|class| {
+        class
+          .allowed_classroom_types
+          .iter()
+          .map(|classroom_type_key| {
+            school_schedule
+              .get_classroom_type_metadata(*classroom_type_key)
+              .unwrap()
+              .name
+              .as_str()
+          })
+          .collect_vec()
+      })
+      .collect_vec();
+    assert_eq!(
+      allowed_classroom_types,
+      vec![vec!["Aula Simple", "Aula Doble"], vec!["Lab Fisica"]]
+    );
+    assert!(constraints.is_lab(constraints.classes.values().nth(1).unwrap()));
+    assert_eq!(school_schedule.class_calendar().class_entries().len(), 3);
+
+    // Saving it again writes the current format, which isn't migrated a second time
+    let json = serde_json::to_string(&school_schedule).unwrap();
+    let school_schedule: SchoolSchedule = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+      school_schedule
+        .get_simulation_constraints()
+        .classroom_types
+        .len(),
+      5
+    );
+  }
 }
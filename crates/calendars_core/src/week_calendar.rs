@@ -1,18 +1,24 @@
+use chrono::{Duration, NaiveTime};
 use serde::{Deserialize, Serialize};
 use std::ops::Range;
 
-const TIMESLOT_COUNT: usize = 12;
-const DAY_COUNT: usize = 5;
-const DATA_LEN: usize = TIMESLOT_COUNT * DAY_COUNT;
+/// A week never has more than seven days.
+const MAX_DAY_COUNT: usize = 7;
+/// Enough for a whole day of 15 minute timeslots.
+const MAX_TIMESLOT_COUNT: usize = 96;
+
+const DEFAULT_DAY_COUNT: usize = 5;
+const DEFAULT_FIRST_HOUR: u32 = 8;
+const DEFAULT_TIMESLOT_COUNT: usize = 12;
 
 const DAY_VALUE_RANGE: Range<usize> = std::ops::Range {
   start: 0,
-  end: DAY_COUNT,
+  end: MAX_DAY_COUNT,
 };
 
 const TIMESLOT_VALUE_RANGE: Range<usize> = std::ops::Range {
   start: 0,
-  end: TIMESLOT_COUNT,
+  end: MAX_TIMESLOT_COUNT,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -35,14 +41,7 @@ const fn range_contains(range: Range<usize>, item: usize) -> bool {
 }
 
 impl Timeslot {
-  pub fn new_random<R: rand::Rng>(rng: &mut R) -> Self {
-    Self(rng.gen_range(TIMESLOT_VALUE_RANGE))
-  }
-
-  pub fn all() -> impl ExactSizeIterator<Item = Self> {
-    TIMESLOT_VALUE_RANGE.map(Timeslot)
-  }
-
+  /// Whether the timeslot exists in a given [`WeekLayout`] is not checked here.
   pub const fn from_usize(value: usize) -> Option<Self> {
     if range_contains(TIMESLOT_VALUE_RANGE, value) {
       Some(Timeslot(value))
@@ -53,14 +52,7 @@ impl Timeslot {
 }
 
 impl Day {
-  pub fn new_random<R: rand::Rng>(rng: &mut R) -> Self {
-    Self(rng.gen_range(DAY_VALUE_RANGE))
-  }
-
-  pub fn all() -> impl ExactSizeIterator<Item = Self> {
-    DAY_VALUE_RANGE.map(Day)
-  }
-
+  /// Whether the day exists in a given [`WeekLayout`] is not checked here.
   pub const fn from_usize(value: usize) -> Option<Self> {
     if range_contains(DAY_VALUE_RANGE, value) {
       Some(Day(value))
@@ -83,6 +75,7 @@ const fn const_unwrap_timeslot(opt: Option<Timeslot>) -> Timeslot {
     None => panic!(),
   }
 }
+// Timeslots of the default week layout, which starts at 08:00.
 pub const TIMESLOT_08_00: Timeslot = const_unwrap_timeslot(Timeslot::from_usize(0));
 pub const TIMESLOT_09_00: Timeslot = const_unwrap_timeslot(Timeslot::from_usize(1));
 pub const TIMESLOT_10_00: Timeslot = const_unwrap_timeslot(Timeslot::from_usize(2));
@@ -95,7 +88,7 @@ pub const TIMESLOT_16_00: Timeslot = const_unwrap_timeslot(Timeslot::from_usize(
 pub const TIMESLOT_17_00: Timeslot = const_unwrap_timeslot(Timeslot::from_usize(9));
 pub const TIMESLOT_18_00: Timeslot = const_unwrap_timeslot(Timeslot::from_usize(10));
 pub const TIMESLOT_19_00: Timeslot = const_unwrap_timeslot(Timeslot::from_usize(11));
-const _: () = assert!(TIMESLOT_19_00.0 == TIMESLOT_COUNT - 1);
+const _: () = assert!(TIMESLOT_19_00.0 == DEFAULT_TIMESLOT_COUNT - 1);
 
 /// const Option::unwrap is not yet stable
 const fn const_unwrap_day(opt: Option<Day>) -> Day {
@@ -109,46 +102,415 @@ pub const DAY_TUESDAY: Day = const_unwrap_day(Day::from_usize(1));
 pub const DAY_WEDNESDAY: Day = const_unwrap_day(Day::from_usize(2));
 pub const DAY_THURSDAY: Day = const_unwrap_day(Day::from_usize(3));
 pub const DAY_FRIDAY: Day = const_unwrap_day(Day::from_usize(4));
-const _: () = assert!(DAY_FRIDAY.0 == DAY_COUNT - 1);
+pub const DAY_SATURDAY: Day = const_unwrap_day(Day::from_usize(5));
+pub const DAY_SUNDAY: Day = const_unwrap_day(Day::from_usize(6));
+const _: () = assert!(DAY_FRIDAY.0 == DEFAULT_DAY_COUNT - 1);
+const _: () = assert!(DAY_SUNDAY.0 == MAX_DAY_COUNT - 1);
+
+#[derive(thiserror::Error, Debug)]
+pub enum InvalidWeekLayoutError {
+  #[error("A week must have between 1 and {MAX_DAY_COUNT} days, got {0}")]
+  DayCount(usize),
+  #[error("A day must have at least one timeslot")]
+  NoTimeslots,
+  #[error("A day can't have more than {MAX_TIMESLOT_COUNT} timeslots")]
+  TooManyTimeslots,
+  #[error("Timeslot {0} must end after it starts")]
+  EmptyTimeslot(usize),
+  #[error("Timeslot {0} starts before the previous one ends")]
+  OverlappingTimeslots(usize),
+  #[error("Timeslots must end before midnight")]
+  PastMidnight,
+}
+
+/// Wall-clock times in which a timeslot starts and ends.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeslotTimes {
+  pub start: NaiveTime,
+  pub end: NaiveTime,
+}
+
+/// Shape of the week: which days and timeslots classes can be scheduled in.
+///
+/// Days always start on Monday. Timeslots are ordered and don't overlap, but they can have any
+/// length and there can be gaps between them (e.g. passing time between periods).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "WeekLayoutData")]
+pub struct WeekLayout {
+  day_count: usize,
+  timeslots: Vec<TimeslotTimes>,
+}
+
+/// Unvalidated [`WeekLayout`], as read from a file.
+#[derive(Deserialize)]
+struct WeekLayoutData {
+  day_count: usize,
+  timeslots: Vec<TimeslotTimes>,
+}
+
+impl TryFrom<WeekLayoutData> for WeekLayout {
+  type Error = InvalidWeekLayoutError;
+
+  fn try_from(data: WeekLayoutData) -> Result<Self, Self::Error> {
+    Self::new(data.day_count, data.timeslots)
+  }
+}
+
+impl Default for WeekLayout {
+  fn default() -> Self {
+    Self::hourly(
+      DEFAULT_DAY_COUNT,
+      DEFAULT_FIRST_HOUR,
+      DEFAULT_TIMESLOT_COUNT,
+    )
+    .unwrap()
+  }
+}
+
+impl WeekLayout {
+  pub fn new(
+    day_count: usize,
+    timeslots: Vec<TimeslotTimes>,
+  ) -> Result<Self, InvalidWeekLayoutError> {
+    if !(1..=MAX_DAY_COUNT).contains(&day_count) {
+      return Err(InvalidWeekLayoutError::DayCount(day_count));
+    }
+    if timeslots.is_empty() {
+      return Err(InvalidWeekLayoutError::NoTimeslots);
+    }
+    if timeslots.len() > MAX_TIMESLOT_COUNT {
+      return Err(InvalidWeekLayoutError::TooManyTimeslots);
+    }
+    for (i, timeslot) in timeslots.iter().enumerate() {
+      if timeslot.end <= timeslot.start {
+        return Err(InvalidWeekLayoutError::EmptyTimeslot(i));
+      }
+      if i > 0 && timeslot.start < timeslots[i - 1].end {
+        return Err(InvalidWeekLayoutError::OverlappingTimeslots(i));
+      }
+    }
+    Ok(Self {
+      day_count,
+      timeslots,
+    })
+  }
+
+  /// One hour timeslots without gaps, starting at `first_hour`. A timeslot ending at midnight
+  /// ends at 23:59:59, since `NaiveTime` can't go past it.
+  pub fn hourly(
+    day_count: usize,
+    first_hour: u32,
+    timeslot_count: usize,
+  ) -> Result<Self, InvalidWeekLayoutError> {
+    if first_hour as usize + timeslot_count > 24 {
+      return Err(InvalidWeekLayoutError::PastMidnight);
+    }
+    let timeslots = (first_hour..)
+      .take(timeslot_count)
+      .map(|hour| TimeslotTimes {
+        start: NaiveTime::from_hms_opt(hour, 0, 0).unwrap(),
+        end: NaiveTime::from_hms_opt(hour + 1, 0, 0)
+          .unwrap_or(NaiveTime::from_hms_opt(23, 59, 59).unwrap()),
+      })
+      .collect();
+    Self::new(day_count, timeslots)
+  }
+
+  /// Timeslots of the same length separated by a fixed passing time, e.g. 50 minute periods
+  /// with 10 minutes between them.
+  pub fn periodic(
+    day_count: usize,
+    first_start: NaiveTime,
+    timeslot_length: Duration,
+    passing_time: Duration,
+    timeslot_count: usize,
+  ) -> Result<Self, InvalidWeekLayoutError> {
+    let mut timeslots = Vec::with_capacity(timeslot_count);
+    let mut start = first_start;
+    for i in 0..timeslot_count {
+      let (end, end_wrapped) = start.overflowing_add_signed(timeslot_length);
+      if end_wrapped != 0 {
+        return Err(InvalidWeekLayoutError::PastMidnight);
+      }
+      timeslots.push(TimeslotTimes { start, end });
+      if i + 1 < timeslot_count {
+        let (next_start, next_start_wrapped) = end.overflowing_add_signed(passing_time);
+        if next_start_wrapped != 0 {
+          return Err(InvalidWeekLayoutError::PastMidnight);
+        }
+        start = next_start;
+      }
+    }
+    Self::new(day_count, timeslots)
+  }
+
+  pub fn day_count(&self) -> usize {
+    self.day_count
+  }
+
+  pub fn timeslot_count(&self) -> usize {
+    self.timeslots.len()
+  }
+
+  pub fn timeslot_times(&self) -> &[TimeslotTimes] {
+    &self.timeslots
+  }
+
+  pub fn days(&self) -> impl ExactSizeIterator<Item = Day> {
+    (0..self.day_count).map(Day)
+  }
+
+  pub fn timeslots(&self) -> impl ExactSizeIterator<Item = Timeslot> {
+    (0..self.timeslots.len()).map(Timeslot)
+  }
+
+  pub fn random_day<R: rand::Rng>(&self, rng: &mut R) -> Day {
+    Day(rng.gen_range(0..self.day_count))
+  }
+
+  pub fn random_timeslot<R: rand::Rng>(&self, rng: &mut R) -> Timeslot {
+    Timeslot(rng.gen_range(0..self.timeslots.len()))
+  }
+
+  pub fn contains(&self, day: Day, timeslot: Timeslot) -> bool {
+    day.0 < self.day_count && timeslot.0 < self.timeslots.len()
+  }
+
+  pub fn timeslot_start(&self, timeslot: Timeslot) -> NaiveTime {
+    self.timeslots[timeslot.0].start
+  }
+
+  pub fn timeslot_end(&self, timeslot: Timeslot) -> NaiveTime {
+    self.timeslots[timeslot.0].end
+  }
+
+  pub fn timeslot_duration(&self, timeslot: Timeslot) -> Duration {
+    self.timeslot_end(timeslot) - self.timeslot_start(timeslot)
+  }
+
+  /// Finds the timeslot that starts at the given time.
+  pub fn find_timeslot(&self, start: NaiveTime) -> Option<Timeslot> {
+    self.timeslots().find(|t| self.timeslot_start(*t) == start)
+  }
+
+  fn data_len(&self) -> usize {
+    self.day_count * self.timeslots.len()
+  }
+}
+
+fn default_day_count() -> usize {
+  DEFAULT_DAY_COUNT
+}
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+fn default_timeslot_count() -> usize {
+  DEFAULT_TIMESLOT_COUNT
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "WeekCalendarData<T>")]
 pub struct WeekCalendar<T> {
+  day_count: usize,
+  timeslot_count: usize,
   data: Vec<T>,
 }
 
+/// Unvalidated [`WeekCalendar`], as read from a file.
+#[derive(Deserialize)]
+struct WeekCalendarData<T> {
+  #[serde(default = "default_day_count")]
+  day_count: usize,
+  #[serde(default = "default_timeslot_count")]
+  timeslot_count: usize,
+  data: Vec<T>,
+}
+
+impl<T> TryFrom<WeekCalendarData<T>> for WeekCalendar<T> {
+  type Error = IncorrectDataLenError;
+
+  fn try_from(data: WeekCalendarData<T>) -> Result<Self, Self::Error> {
+    if data.data.len() != data.day_count * data.timeslot_count {
+      return Err(IncorrectDataLenError {});
+    }
+    Ok(Self {
+      day_count: data.day_count,
+      timeslot_count: data.timeslot_count,
+      data: data.data,
+    })
+  }
+}
+
 impl<T: Default + Clone> Default for WeekCalendar<T> {
   fn default() -> Self {
+    Self::new(&WeekLayout::default())
+  }
+}
+
+impl<T: Clone> WeekCalendar<T> {
+  pub fn filled(week_layout: &WeekLayout, value: T) -> Self {
     Self {
-      data: vec![Default::default(); DATA_LEN],
+      day_count: week_layout.day_count,
+      timeslot_count: week_layout.timeslot_count(),
+      data: vec![value; week_layout.data_len()],
     }
   }
 }
 
-const fn get_index(day: Day, timeslot: Timeslot) -> usize {
-  day.0 * TIMESLOT_COUNT + timeslot.0
+impl<T: Default + Clone> WeekCalendar<T> {
+  pub fn new(week_layout: &WeekLayout) -> Self {
+    Self::filled(week_layout, Default::default())
+  }
+
+  /// Copies the calendar into a new layout. Cells are matched by day and by timeslot start time,
+  /// cells that don't exist in the old layout are filled with the default value.
+  pub fn with_week_layout(&self, old_layout: &WeekLayout, new_layout: &WeekLayout) -> Self {
+    let mut calendar = Self::new(new_layout);
+    for day in new_layout.days() {
+      for timeslot in new_layout.timeslots() {
+        if let Some(old_timeslot) = old_layout.find_timeslot(new_layout.timeslot_start(timeslot)) {
+          if old_layout.contains(day, old_timeslot) {
+            *calendar.get_mut(day, timeslot) = self.get(day, old_timeslot).clone();
+          }
+        }
+      }
+    }
+    calendar
+  }
 }
 
 impl<T> WeekCalendar<T> {
   pub fn get(&self, day: Day, timeslot: Timeslot) -> &T {
-    &self.data[get_index(day, timeslot)]
+    &self.data[self.get_index(day, timeslot)]
   }
 
   pub fn get_mut(&mut self, day: Day, timeslot: Timeslot) -> &mut T {
-    &mut self.data[get_index(day, timeslot)]
+    let index = self.get_index(day, timeslot);
+    &mut self.data[index]
+  }
+
+  pub fn from_vec(week_layout: &WeekLayout, data: Vec<T>) -> Result<Self, IncorrectDataLenError> {
+    if data.len() == week_layout.data_len() {
+      Ok(Self {
+        day_count: week_layout.day_count,
+        timeslot_count: week_layout.timeslot_count(),
+        data,
+      })
+    } else {
+      Err(IncorrectDataLenError {})
+    }
+  }
+
+  /// Whether the calendar has the days and timeslots of `week_layout`.
+  pub(crate) fn fits(&self, week_layout: &WeekLayout) -> bool {
+    self.day_count == week_layout.day_count && self.timeslot_count == week_layout.timeslot_count()
+  }
+
+  fn get_index(&self, day: Day, timeslot: Timeslot) -> usize {
+    assert!(
+      day.0 < self.day_count && timeslot.0 < self.timeslot_count,
+      "{day:?} {timeslot:?} is outside of the week calendar"
+    );
+    day.0 * self.timeslot_count + timeslot.0
   }
 }
 
 #[derive(thiserror::Error, Debug)]
 #[error("Provided data length is incorrect")]
 pub struct IncorrectDataLenError {}
-impl<T> TryFrom<Vec<T>> for WeekCalendar<T> {
-  type Error = IncorrectDataLenError;
 
-  fn try_from(data: Vec<T>) -> Result<Self, Self::Error> {
-    if data.len() == DATA_LEN {
-      Ok(Self { data })
-    } else {
-      Err(IncorrectDataLenError {})
-    }
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_with_week_layout() {
+    let old_layout = WeekLayout::default();
+    let new_layout = WeekLayout::hourly(6, 7, 14).unwrap();
+    let mut calendar: WeekCalendar<u8> = WeekCalendar::new(&old_layout);
+    *calendar.get_mut(DAY_MONDAY, TIMESLOT_08_00) = 1;
+    *calendar.get_mut(DAY_FRIDAY, TIMESLOT_19_00) = 2;
+    let calendar = calendar.with_week_layout(&old_layout, &new_layout);
+    let t07_00 = new_layout
+      .find_timeslot(NaiveTime::from_hms_opt(7, 0, 0).unwrap())
+      .unwrap();
+    let t08_00 = new_layout
+      .find_timeslot(NaiveTime::from_hms_opt(8, 0, 0).unwrap())
+      .unwrap();
+    let t19_00 = new_layout
+      .find_timeslot(NaiveTime::from_hms_opt(19, 0, 0).unwrap())
+      .unwrap();
+    assert_eq!(*calendar.get(DAY_MONDAY, t07_00), 0);
+    assert_eq!(*calendar.get(DAY_MONDAY, t08_00), 1);
+    assert_eq!(*calendar.get(DAY_FRIDAY, t19_00), 2);
+    assert_eq!(*calendar.get(DAY_SATURDAY, t08_00), 0);
+  }
+
+  #[test]
+  fn test_invalid_week_layout() {
+    assert!(WeekLayout::hourly(0, 8, 12).is_err());
+    assert!(WeekLayout::hourly(8, 8, 12).is_err());
+    assert!(WeekLayout::hourly(5, 8, 0).is_err());
+    assert!(WeekLayout::hourly(5, 13, 12).is_err());
+    assert!(WeekLayout::hourly(7, 0, 24).is_ok());
+    let t = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    assert!(matches!(
+      WeekLayout::new(
+        5,
+        vec![
+          TimeslotTimes {
+            start: t(8, 0),
+            end: t(9, 0)
+          },
+          TimeslotTimes {
+            start: t(8, 30),
+            end: t(9, 30)
+          },
+        ]
+      ),
+      Err(InvalidWeekLayoutError::OverlappingTimeslots(1))
+    ));
+    assert!(matches!(
+      WeekLayout::new(
+        5,
+        vec![TimeslotTimes {
+          start: t(8, 0),
+          end: t(8, 0)
+        }]
+      ),
+      Err(InvalidWeekLayoutError::EmptyTimeslot(0))
+    ));
+  }
+
+  #[test]
+  fn test_deserialize_week_layout() {
+    let week_layout = WeekLayout::hourly(6, 7, 14).unwrap();
+    let json = serde_json::to_string(&week_layout).unwrap();
+    assert_eq!(
+      serde_json::from_str::<WeekLayout>(&json).unwrap(),
+      week_layout
+    );
+    let empty_timeslot = r#"{"day_count":5,"timeslots":[{"start":"09:00:00","end":"08:00:00"}]}"#;
+    assert!(serde_json::from_str::<WeekLayout>(empty_timeslot).is_err());
+    assert!(serde_json::from_str::<WeekLayout>(r#"{"day_count":0,"timeslots":[]}"#).is_err());
+
+    let calendar = WeekCalendar::filled(&week_layout, 1u8);
+    let json = serde_json::to_string(&calendar).unwrap();
+    assert!(serde_json::from_str::<WeekCalendar<u8>>(&json).is_ok());
+    let short = json.replace("[1,", "[");
+    assert!(serde_json::from_str::<WeekCalendar<u8>>(&short).is_err());
+  }
+
+  #[test]
+  fn test_periodic_week_layout() {
+    let t = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    let week_layout =
+      WeekLayout::periodic(5, t(7, 0), Duration::minutes(50), Duration::minutes(10), 14).unwrap();
+    assert_eq!(week_layout.timeslot_count(), 14);
+    let last = Timeslot::from_usize(13).unwrap();
+    assert_eq!(week_layout.timeslot_start(last), t(20, 0));
+    assert_eq!(week_layout.timeslot_end(last), t(20, 50));
+    assert_eq!(week_layout.timeslot_duration(last), Duration::minutes(50));
+    assert!(
+      WeekLayout::periodic(5, t(22, 0), Duration::minutes(50), Duration::minutes(10), 3).is_err()
+    );
   }
 }
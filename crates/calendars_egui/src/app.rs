@@ -1,17 +1,21 @@
-use std::{
-  sync::mpsc::{Receiver, Sender},
-  thread::JoinHandle,
-};
+use std::{sync::Arc, thread::JoinHandle};
 
 use crate::{
-  class_editor::ClassEditor, database_importer, optimization_widget::OptimizationWidget,
-  professor_editor::ProfessorEditor, professor_schedule_widget::ProfessorScheduleWidget,
+  availability_widget::AvailabilityWidget,
+  class_editor::ClassEditor,
+  classroom_editor::ClassroomEditor,
+  database_importer,
+  egui_progress::EguiProgress,
+  optimization_widget::{OptimizationMethod, OptimizationResult, OptimizationWidget},
+  professor_editor::ProfessorEditor,
   simple_schedule_widget::SimpleScheduleWidget,
+  violations_widget::ViolationsWidget,
+  week_layout_editor::WeekLayoutEditor,
 };
-use calendars_core::ClassCalendarOptimizer;
 use calendars_core::{
-  AdvancedSimulationOptions, ClassCalendar, LiveUpdate, ProfessorKey, ProgressOption,
-  SchoolSchedule, SimulationOptions, SimulationOutput, TemperatureFunction,
+  greedy_class_calendar, AnnealingConfig, ExactSolver, ExactSolverOptions, ProfessorKey,
+  RunControl, SchoolSchedule, SimulatedAnnealingOptimizer, StopCondition, TabuSearchOptimizer,
+  TabuSearchOptions,
 };
 use egui::Ui;
 use rfd::FileDialog;
@@ -19,9 +23,8 @@ use serde::{Deserialize, Serialize};
 use tracing::info;
 
 struct CurrentSimulation {
-  progress_bar: indicatif::ProgressBar,
-  live_update: std::sync::mpsc::Receiver<ClassCalendar>,
-  join_handle: JoinHandle<Vec<SimulationOutput>>,
+  progress: EguiProgress,
+  join_handle: JoinHandle<anyhow::Result<Vec<OptimizationResult>>>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -32,11 +35,17 @@ pub struct MyApp {
   professor_editor_widget_open: bool,
   class_editor_widget_open: bool,
   class_editor: ClassEditor,
+  classroom_editor: ClassroomEditor,
   optimization_widget: OptimizationWidget,
+  violations_widget: ViolationsWidget,
+  week_layout_editor: WeekLayoutEditor,
   availability_editor_professor_key: Option<ProfessorKey>,
   availability_editor_widget_open: bool,
   #[serde(skip)]
   current_simulation: Option<CurrentSimulation>,
+  /// Shown until dismissed.
+  #[serde(skip)]
+  error_message: Option<String>,
   pub developer_mode: bool,
 }
 
@@ -47,6 +56,113 @@ impl MyApp {
     }
     Default::default()
   }
+  /// Replaces the schedule with one of the results of the last optimization.
+  fn apply_simulation_result(&mut self, i: usize) {
+    let new_class_calendar = self.optimization_widget.results[i].best_calendar().clone();
+    if let Err(e) = self
+      .school_schedule
+      .replace_class_calendar(new_class_calendar)
+    {
+      self.error_message = Some(format!("No se pudo aplicar el resultado: {e}"));
+      return;
+    }
+    self.optimization_widget.applied_result = Some(i);
+    self.optimization_widget.hard_constraint_violations =
+      Some(self.school_schedule.hard_constraint_violations());
+    self.optimization_widget.cost_breakdown = self.school_schedule.cost_breakdown();
+    self.violations_widget.refresh(&self.school_schedule);
+    info!("Applied new schedule");
+  }
+
+  /// Runs the method chosen in the optimization widget on another thread.
+  fn start_optimization(
+    &mut self,
+    ctx: &egui::Context,
+    stop_condition: StopCondition,
+  ) -> anyhow::Result<CurrentSimulation> {
+    let progress = EguiProgress::new(ctx.clone());
+    let constraints = self.school_schedule.get_simulation_constraints().clone();
+    let initial_state = if self.optimization_widget.greedy_start {
+      greedy_class_calendar(&constraints, self.school_schedule.class_calendar())
+    } else {
+      self.school_schedule.class_calendar().clone()
+    };
+    let heuristic_weights = self.school_schedule.heuristic_weights().clone();
+    let seed = self.optimization_widget.seed;
+    self.optimization_widget.control = RunControl::default();
+    let control = self.optimization_widget.control.clone();
+    let run: Box<dyn FnOnce() -> anyhow::Result<Vec<OptimizationResult>> + Send> =
+      match self.optimization_widget.method {
+        OptimizationMethod::SimulatedAnnealing => {
+          let runs = self.optimization_widget.runs.max(1);
+          let mut config = AnnealingConfig::new(initial_state)
+            .stop_condition(stop_condition)
+            .temperature_schedule(self.optimization_widget.temperature_function.clone())
+            .initial_temperature(self.optimization_widget.initial_temperature.clone())
+            .heuristic_weights(heuristic_weights)
+            .control(control)
+            .progress_observer(progress.clone());
+          if let Some(seed) = seed {
+            config = config.seed(seed);
+          }
+          Box::new(move || {
+            Ok(
+              SimulatedAnnealingOptimizer::default()
+                .run_multi_start(&constraints, config, runs)
+                .into_iter()
+                .map(|output| OptimizationResult::SimulatedAnnealing(Box::new(output)))
+                .collect(),
+            )
+          })
+        }
+        OptimizationMethod::TabuSearch => {
+          let StopCondition::Steps(iterations) = stop_condition else {
+            anyhow::bail!("la busqueda tabu solo se detiene tras un numero de pasos");
+          };
+          let options = TabuSearchOptions {
+            initial_state,
+            iterations,
+            heuristic_weights,
+            seed,
+            progress: Some(Arc::new(progress.clone())),
+            control,
+            ..Default::default()
+          };
+          Box::new(move || {
+            Ok(vec![OptimizationResult::TabuSearch(Box::new(
+              TabuSearchOptimizer::default().run(&constraints, options),
+            ))])
+          })
+        }
+        OptimizationMethod::Exact => {
+          let StopCondition::Steps(max_nodes) = stop_condition else {
+            anyhow::bail!("el metodo exacto solo se detiene tras un numero de pasos");
+          };
+          let options = ExactSolverOptions {
+            initial_state,
+            max_nodes: max_nodes as u64,
+            heuristic_weights,
+            ..Default::default()
+          };
+          Box::new(move || {
+            let solution = ExactSolver.solve(&constraints, options)?;
+            Ok(vec![OptimizationResult::Exact(Box::new(solution))])
+          })
+        }
+      };
+    let local_ctx = ctx.clone();
+    let join_handle = std::thread::spawn(move || {
+      let results = run();
+      // Wake the app up so it collects the results
+      local_ctx.request_repaint();
+      results
+    });
+    Ok(CurrentSimulation {
+      progress,
+      join_handle,
+    })
+  }
+
   fn draw_menu_bar(&mut self, ui: &mut Ui) {
     egui::menu::bar(ui, |ui| {
       ui.menu_button("Archivo", |ui| {
@@ -59,8 +175,9 @@ impl MyApp {
             .add_filter("horario", &["horario"])
             .save_file()
           {
-            let schedule = self.school_schedule.clone();
-            std::fs::write(path, serde_json::to_string(&schedule).unwrap()).unwrap();
+            if let Err(e) = save_schedule(&path, &self.school_schedule) {
+              self.error_message = Some(format!("No se pudo guardar el horario: {e}"));
+            }
           }
         }
         if ui.button("Cargar").clicked() {
@@ -69,9 +186,10 @@ impl MyApp {
             .add_filter("horario", &["horario"])
             .pick_file()
           {
-            let buf = std::fs::read_to_string(path).unwrap();
-            let schedule: SchoolSchedule = serde_json::from_str(&buf).unwrap();
-            self.school_schedule = schedule;
+            match load_schedule(&path) {
+              Ok(schedule) => self.school_schedule = schedule,
+              Err(e) => self.error_message = Some(format!("No se pudo cargar el horario: {e}")),
+            }
           }
         }
         if ui.button("Importar SQLs").clicked() {
@@ -87,9 +205,35 @@ impl MyApp {
         if ui.button("Editor de Clases").clicked() {
           self.class_editor_widget_open = !self.class_editor_widget_open;
         }
+        if ui.button("Editor de Aulas").clicked() {
+          self.classroom_editor.open = !self.classroom_editor.open;
+        }
         if ui.button("Calendario").clicked() {
           self.schedule_widget.open = !self.schedule_widget.open;
         }
+        if ui.button("Semana").clicked() {
+          self.week_layout_editor.open = !self.week_layout_editor.open;
+        }
+        if ui.button("Violaciones").clicked() {
+          self.violations_widget.open = !self.violations_widget.open;
+          self.violations_widget.refresh(&self.school_schedule);
+        }
+      });
+      ui.menu_button("Horario", |ui| {
+        if ui
+          .add_enabled(
+            self.current_simulation.is_none(),
+            egui::Button::new("Generar horario inicial"),
+          )
+          .on_hover_text("Acomoda las clases no fijadas evitando conflictos.")
+          .clicked()
+        {
+          self.school_schedule.generate_initial_class_calendar();
+          self.optimization_widget.hard_constraint_violations =
+            Some(self.school_schedule.hard_constraint_violations());
+          self.optimization_widget.cost_breakdown = self.school_schedule.cost_breakdown();
+          self.violations_widget.refresh(&self.school_schedule);
+        }
       });
     });
   }
@@ -113,21 +257,39 @@ impl eframe::App for MyApp {
 
       self.draw_menu_bar(ui);
 
+      if let Some(error_message) = self.error_message.clone() {
+        egui::Window::new("Error")
+          .collapsible(false)
+          .resizable(false)
+          .show(ctx, |ui| {
+            ui.colored_label(ui.visuals().error_fg_color, error_message);
+            if ui.button("Cerrar").clicked() {
+              self.error_message = None;
+            }
+          });
+      }
+
       if let Some(current_simulation) = self.current_simulation.as_ref() {
-        let mut latest_result = None;
-        while let Ok(result) = current_simulation.live_update.try_recv() {
-          latest_result = Some(result);
-        }
-        if let Some(latest_result) = latest_result {
-          self
-            .school_schedule
-            .replace_class_calendar(latest_result)
-            .unwrap();
+        if let Some(latest_result) = current_simulation.progress.take_best_calendar() {
+          if let Err(e) = self.school_schedule.replace_class_calendar(latest_result) {
+            info!("Dropped the preview of the optimization: {e}");
+          }
         }
       }
-      self.schedule_widget.show(ctx, &self.school_schedule);
+      self.schedule_widget.highlighted_cells = self.violations_widget.highlighted_cells();
+      self.schedule_widget.show(ctx, &mut self.school_schedule);
+      self.violations_widget.show(ctx, &self.school_schedule);
+
+      // The running optimization works on a copy of the classes, hours and week layout, and its
+      // results can only be applied while they stay the same.
+      let editable = self.current_simulation.is_none();
+      self
+        .class_editor
+        .show(ctx, &mut self.school_schedule, editable);
 
-      self.class_editor.show(ctx, &mut self.school_schedule);
+      self
+        .week_layout_editor
+        .show(ctx, &mut self.school_schedule, editable);
 
       ProfessorEditor::new(
         &mut self.school_schedule,
@@ -136,103 +298,30 @@ impl eframe::App for MyApp {
       )
       .show(ctx, &mut self.professor_editor_widget_open);
 
+      self
+        .classroom_editor
+        .show(ctx, &mut self.school_schedule, editable);
+
       if let Some(professor_id) = self.availability_editor_professor_key {
+        let week_layout = self.school_schedule.week_layout().clone();
         if let Some(professor) = self.school_schedule.get_professor_mut(professor_id) {
-          ProfessorScheduleWidget::new(professor)
+          AvailabilityWidget::new("Profesor", &mut professor.availability, &week_layout)
             .show(ctx, &mut self.availability_editor_widget_open);
         }
       }
 
       if let Some(stop_condition) = self.optimization_widget.show(
         ctx,
-        self.current_simulation.as_ref().map(|x| &x.progress_bar),
+        self
+          .current_simulation
+          .as_ref()
+          .map(|x| x.progress.fraction_done()),
+        self.school_schedule.heuristic_weights_mut(),
       ) {
-        self.current_simulation = {
-          let (live_update_simulation_tx, live_update_proxy_rx): (
-            Sender<ClassCalendar>,
-            Receiver<ClassCalendar>,
-          ) = std::sync::mpsc::channel();
-          let (live_update_proxy_tx, live_update_app_rx): (
-            Sender<ClassCalendar>,
-            Receiver<ClassCalendar>,
-          ) = std::sync::mpsc::channel();
-          let advanced_options = AdvancedSimulationOptions {
-            live_update: Some(LiveUpdate {
-              channel: live_update_simulation_tx,
-              live_update_interval: 5_000,
-            }),
-            ..Default::default()
-          };
-          let progress_bar = indicatif::ProgressBar::hidden();
-          let pb = progress_bar.clone();
-          let local_simulation_constraints =
-            self.school_schedule.get_simulation_constraints().clone();
-          let local_ctx = ctx.clone();
-          let initial_state = self.school_schedule.class_calendar().clone();
-          let join_handle = std::thread::spawn(move || {
-            let pb2 = pb.clone();
-            let pb_ctx = local_ctx.clone();
-            let pb_thread = std::thread::spawn(move || {
-              let mut p = pb2.position();
-              pb_ctx.request_repaint();
-              loop {
-                if pb2.position() != p {
-                  p = pb2.position();
-                  pb_ctx.request_repaint();
-                }
-                std::thread::sleep(std::time::Duration::from_millis(100));
-              }
-            });
-            let live_update_ctx = local_ctx.clone();
-            let live_update_proxy_thread = std::thread::spawn(move || {
-              while let Ok(val) = live_update_proxy_rx.recv() {
-                match live_update_proxy_tx.send(val) {
-                  Ok(_) => live_update_ctx.request_repaint(), // live update sent succesfully, issue repaint
-                  Err(_) => break, // app thread channel no longer active, exit thread
-                }
-              }
-              // simulation thread channel no longer active, exit thread
-            });
-            let mut optimizer = calendars_core::SimulatedAnnealingOptimizer::default();
-            let simulation_thread: JoinHandle<Vec<SimulationOutput>> =
-              std::thread::spawn(move || {
-                let options = SimulationOptions {
-                  stop_condition,
-                  initial_state,
-                  temperature_function: TemperatureFunction::Linear,
-                  progress: ProgressOption::ProgressBar(pb),
-                  advanced_options,
-                };
-                let class_calendar = optimizer.generate_class_calendar(
-                  local_simulation_constraints,
-                  options.clone(),
-                  None,
-                );
-                let simulation_output: SimulationOutput = SimulationOutput {
-                  simulation_options: options,
-                  final_calendar: class_calendar,
-                  final_cost: Default::default(),
-                  start_time: std::time::SystemTime::UNIX_EPOCH,
-                  end_time: std::time::SystemTime::UNIX_EPOCH,
-                  duration: Default::default(),
-                  stats: Default::default(),
-                  total_steps: Default::default(),
-                  classroom_assignments: Default::default(),
-                };
-                vec![simulation_output]
-              });
-            let r = simulation_thread.join().unwrap();
-            drop(pb_thread);
-            drop(live_update_proxy_thread);
-            local_ctx.request_repaint();
-            r
-          });
-          Some(CurrentSimulation {
-            progress_bar,
-            join_handle,
-            live_update: live_update_app_rx,
-          })
-        };
+        match self.start_optimization(ctx, stop_condition) {
+          Ok(current_simulation) => self.current_simulation = Some(current_simulation),
+          Err(e) => self.error_message = Some(format!("No se pudo optimizar: {e}")),
+        }
       }
 
       if self.current_simulation.is_some() {
@@ -244,21 +333,37 @@ impl eframe::App for MyApp {
           .is_finished();
         if is_finished {
           let simulation_output = self.current_simulation.take();
-          let simulation_output = simulation_output.unwrap().join_handle.join().unwrap();
-          save_latest_simulation_output(&simulation_output).unwrap();
-          let new_class_calendar = simulation_output.into_iter().nth(0).unwrap().final_calendar;
-          self
-            .school_schedule
-            .replace_class_calendar(new_class_calendar)
-            .unwrap();
-          info!("Applied new schedule");
+          match simulation_output.unwrap().join_handle.join().unwrap() {
+            Ok(simulation_output) => {
+              save_latest_simulation_output(&simulation_output).unwrap();
+              self.optimization_widget.results = simulation_output;
+              self.apply_simulation_result(0);
+            }
+            Err(e) => self.error_message = Some(format!("No se pudo optimizar: {e}")),
+          }
         }
       }
+
+      if let Some(i) = self.optimization_widget.result_to_apply.take() {
+        self.apply_simulation_result(i);
+      }
     });
   }
 }
 
-fn save_latest_simulation_output(simulation_output: &Vec<SimulationOutput>) -> anyhow::Result<()> {
+fn save_schedule(path: &std::path::Path, schedule: &SchoolSchedule) -> anyhow::Result<()> {
+  std::fs::write(path, serde_json::to_string(schedule)?)?;
+  Ok(())
+}
+
+fn load_schedule(path: &std::path::Path) -> anyhow::Result<SchoolSchedule> {
+  let buf = std::fs::read_to_string(path)?;
+  Ok(serde_json::from_str(&buf)?)
+}
+
+fn save_latest_simulation_output(
+  simulation_output: &Vec<OptimizationResult>,
+) -> anyhow::Result<()> {
   let cwd = std::env::current_dir()?;
   let cwd = std::path::Path::new(&cwd);
   let t = chrono::Local::now();
@@ -282,8 +387,12 @@ impl Default for MyApp {
       availability_editor_professor_key: None,
       availability_editor_widget_open: true,
       current_simulation: None,
+      error_message: None,
       class_editor: Default::default(),
+      classroom_editor: Default::default(),
       optimization_widget: Default::default(),
+      violations_widget: Default::default(),
+      week_layout_editor: Default::default(),
       developer_mode: false,
       schedule_widget: Default::default(),
     }
@@ -1,16 +1,28 @@
-use calendars_core::{Availability, Day, Professor, Timeslot};
+use calendars_core::{Availability, WeekCalendar, WeekLayout};
 use egui::Color32;
 
-pub struct ProfessorScheduleWidget<'a> {
-  state: &'a mut Professor,
+use crate::simple_schedule_widget::{timeslot_label, DAY_NAMES};
+
+pub struct AvailabilityWidget<'a> {
+  title: &'a str,
+  state: &'a mut WeekCalendar<Availability>,
+  week_layout: &'a WeekLayout,
 }
 
-impl<'a> ProfessorScheduleWidget<'a> {
-  pub fn new(state: &'a mut Professor) -> ProfessorScheduleWidget<'a> {
-    ProfessorScheduleWidget { state }
+impl<'a> AvailabilityWidget<'a> {
+  pub fn new(
+    title: &'a str,
+    state: &'a mut WeekCalendar<Availability>,
+    week_layout: &'a WeekLayout,
+  ) -> AvailabilityWidget<'a> {
+    AvailabilityWidget {
+      title,
+      state,
+      week_layout,
+    }
   }
   pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) {
-    egui::Window::new("Profesor")
+    egui::Window::new(self.title)
       .open(open)
       .vscroll(false)
       .resizable(true)
@@ -20,10 +32,16 @@ impl<'a> ProfessorScheduleWidget<'a> {
       });
   }
   fn ui(&mut self, ui: &mut egui::Ui) {
-    egui::Grid::new("my_grid").show(ui, |ui| {
-      for j in Timeslot::all() {
-        for i in Day::all() {
-          let av = (self.state.availability).get_mut(i, j);
+    egui::Grid::new(("availability_grid", self.title)).show(ui, |ui| {
+      ui.label("");
+      for i in self.week_layout.days() {
+        ui.label(DAY_NAMES[usize::from(i)]);
+      }
+      ui.end_row();
+      for j in self.week_layout.timeslots() {
+        ui.label(timeslot_label(self.week_layout, j));
+        for i in self.week_layout.days() {
+          let av = self.state.get_mut(i, j);
           let text = match av {
             Availability::Available => "1",
             Availability::AvailableIfNeeded => "2",
@@ -3,8 +3,7 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use calendars_core::{
-  enumflags2::BitFlags, strum::IntoEnumIterator, ClassKey, ClassroomType, Group, SchoolSchedule,
-  Semester,
+  strum::IntoEnumIterator, ClassKey, Group, SchoolSchedule, Semester, WeekLayout,
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -23,10 +22,11 @@ impl Default for ClassEditor {
 }
 
 impl ClassEditor {
-  pub fn show(&mut self, ctx: &egui::Context, state: &mut SchoolSchedule) {
+  pub fn show(&mut self, ctx: &egui::Context, state: &mut SchoolSchedule, enabled: bool) {
     let mut open = self.open;
     egui::Window::new("Clases")
       .open(&mut open)
+      .enabled(enabled)
       .resizable(true)
       .show(ctx, |ui| {
         self.ui(ui, state);
@@ -66,27 +66,29 @@ impl ClassEditor {
             ui.label(RichText::new("Aulas Permitidas").strong()).on_hover_text(
               "El programa intentara asignar una aula de las categorias seleccionadas a esta clase.",
             );
-            let curr_classroom_types = state
+            let curr_classroom_types = &state
               .get_class(class_key)
               .unwrap()
               .allowed_classroom_types;
-            let classroom_types = ClassroomType::iter()
-              .map(|v| (v, curr_classroom_types.contains(v)))
-              .collect_vec();
-            let classroom_types = classroom_types
-              .into_iter()
-              .map(|(v, mut curr)| {
-                if !matches!(v, ClassroomType::NotAssigned) {
-                  ui.checkbox(&mut curr, v.to_string());
-                }
-                (v, curr)
-              })
-              .filter(|(_v, curr)| *curr)
-              .map(|(v, _curr)| v)
+            let classroom_types = state
+              .get_simulation_constraints()
+              .classroom_types
+              .keys()
+              .map(|k| (k, curr_classroom_types.contains(&k)))
               .collect_vec();
             let new_classroom_types = classroom_types
               .into_iter()
-              .fold(BitFlags::empty(), |a, b| a | b);
+              .map(|(k, mut curr)| {
+                let name = state
+                  .get_classroom_type_metadata(k)
+                  .map(|classroom_type_metadata| classroom_type_metadata.name.as_str())
+                  .unwrap_or("Tipo de aula indefinido");
+                ui.checkbox(&mut curr, name);
+                (k, curr)
+              })
+              .filter(|(_k, curr)| *curr)
+              .map(|(k, _curr)| k)
+              .collect();
             state
               .get_class_entry(class_key)
               .unwrap()
@@ -177,10 +179,20 @@ impl ClassEditor {
               }
             })
         });
+        ui.horizontal(|ui| {
+          ui.label("Alumnos");
+          let mut enrollment = state.get_class(class_key).unwrap().enrollment;
+          ui.add(egui::DragValue::new(&mut enrollment));
+          state
+            .get_class_entry(class_key)
+            .unwrap()
+            .set_enrollment(enrollment);
+        });
         ui.horizontal(|ui| {
           let original_class_hours = state.get_class(class_key).unwrap().class_hours;
           let mut class_hours = original_class_hours;
-          ui.add(egui::Slider::new(&mut class_hours, 0..=20).text(to_human_time(original_class_hours)));
+          let human_time = to_human_time(original_class_hours, state.week_layout());
+          ui.add(egui::Slider::new(&mut class_hours, 0..=20).text(human_time));
           state
             .get_class_entry(class_key)
             .unwrap()
@@ -212,11 +224,20 @@ impl ClassEditor {
   }
 }
 
-fn to_human_time(class_hours: u8) -> String {
-  // each unit is worth 60 minutes
-  let hours = class_hours;
-  match hours == 1 {
-    true => "1 hr.".to_string(),
-    false => format!("{} hrs.", hours),
+fn to_human_time(class_hours: u8, week_layout: &WeekLayout) -> String {
+  // each unit is worth one timeslot, which can have any length
+  let durations = week_layout
+    .timeslots()
+    .map(|t| week_layout.timeslot_duration(t).num_minutes())
+    .collect_vec();
+  let is_uniform = durations.iter().all_equal();
+  let minutes = durations.iter().sum::<i64>() * class_hours as i64 / durations.len() as i64;
+  let (hours, minutes) = (minutes / 60, minutes % 60);
+  let prefix = if is_uniform { "" } else { "~" };
+  match (hours == 1, minutes) {
+    (true, 0) => format!("{prefix}1 hr."),
+    (false, 0) => format!("{prefix}{} hrs.", hours),
+    (true, _) => format!("{prefix}1 hr. {} min.", minutes),
+    (false, _) => format!("{prefix}{} hrs. {} min.", hours, minutes),
   }
 }
@@ -0,0 +1,188 @@
+use calendars_core::{Classroom, ClassroomKey, ClassroomTypeKey, SchoolSchedule};
+use egui::{ComboBox, RichText, ScrollArea};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::availability_widget::AvailabilityWidget;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClassroomEditor {
+  pub open: bool,
+  availability_editor_classroom_key: Option<ClassroomKey>,
+  availability_editor_open: bool,
+}
+
+impl Default for ClassroomEditor {
+  fn default() -> Self {
+    Self {
+      open: true,
+      availability_editor_classroom_key: None,
+      availability_editor_open: false,
+    }
+  }
+}
+
+impl ClassroomEditor {
+  pub fn show(&mut self, ctx: &egui::Context, state: &mut SchoolSchedule, enabled: bool) {
+    let classrooms_before = classrooms(state);
+    let mut open = self.open;
+    egui::Window::new("Editor de aulas")
+      .open(&mut open)
+      .enabled(enabled)
+      .resizable(true)
+      .show(ctx, |ui| {
+        self.ui(ui, state);
+      });
+    self.open = open;
+
+    if let Some(classroom_key) = self.availability_editor_classroom_key {
+      let week_layout = state.week_layout().clone();
+      if let Some(classroom) = state.get_classroom_mut(classroom_key) {
+        AvailabilityWidget::new("Aula", &mut classroom.availability, &week_layout)
+          .show(ctx, &mut self.availability_editor_open);
+      }
+    }
+
+    if classrooms(state) != classrooms_before {
+      state.update_classroom_assignments();
+    }
+  }
+
+  fn classroom_type_entry(
+    &mut self,
+    ui: &mut egui::Ui,
+    state: &mut SchoolSchedule,
+    classroom_type_key: ClassroomTypeKey,
+  ) {
+    ui.horizontal(|ui| {
+      ui.text_edit_singleline(
+        &mut state
+          .get_classroom_type_metadata_mut(classroom_type_key)
+          .unwrap()
+          .name,
+      );
+      ui.checkbox(
+        &mut state
+          .get_classroom_type_mut(classroom_type_key)
+          .unwrap()
+          .lab,
+        "Laboratorio",
+      );
+    });
+  }
+
+  fn classroom_entry(
+    &mut self,
+    ui: &mut egui::Ui,
+    state: &mut SchoolSchedule,
+    classroom_key: ClassroomKey,
+  ) {
+    ui.horizontal(|ui| {
+      ui.label("Nombre");
+      ui.text_edit_singleline(
+        &mut state
+          .get_classroom_metadata_mut(classroom_key)
+          .unwrap()
+          .name,
+      );
+    });
+    ui.horizontal(|ui| {
+      ui.label("Tipo");
+      ComboBox::from_id_source(egui::Id::new(("classroom_type_combo_box", classroom_key)))
+        .selected_text(
+          state
+            .get_classroom_type_metadata(state.get_classroom(classroom_key).unwrap().classroom_type)
+            .map(|classroom_type_metadata| classroom_type_metadata.name.as_str())
+            .unwrap_or("Tipo de aula indefinido"),
+        )
+        .show_ui(ui, |ui| {
+          let classroom_type_keys = state
+            .get_simulation_constraints()
+            .classroom_types
+            .keys()
+            .collect_vec();
+          let mut classroom_type = state.get_classroom(classroom_key).unwrap().classroom_type;
+          for classroom_type_key in classroom_type_keys {
+            ui.selectable_value(
+              &mut classroom_type,
+              classroom_type_key,
+              state
+                .get_classroom_type_metadata(classroom_type_key)
+                .unwrap()
+                .name
+                .as_str(),
+            );
+          }
+          state
+            .get_classroom_mut(classroom_key)
+            .unwrap()
+            .classroom_type = classroom_type;
+        });
+    });
+    ui.horizontal(|ui| {
+      let capacity = &mut state.get_classroom_mut(classroom_key).unwrap().capacity;
+      let mut limited_capacity = capacity.is_some();
+      ui.checkbox(&mut limited_capacity, "Capacidad limitada");
+      match (limited_capacity, capacity.as_mut()) {
+        (true, Some(capacity)) => {
+          ui.add(egui::DragValue::new(capacity));
+        }
+        (true, None) => *capacity = Some(0),
+        (false, _) => *capacity = None,
+      }
+    });
+    if ui.button("Editar disponibilidad").clicked() {
+      self.availability_editor_classroom_key = Some(classroom_key);
+      self.availability_editor_open = true;
+    }
+    ui.separator();
+  }
+
+  fn ui(&mut self, ui: &mut egui::Ui, state: &mut SchoolSchedule) {
+    ui.label(RichText::new("Tipos de aula").strong());
+    let classroom_type_keys = state
+      .get_simulation_constraints()
+      .classroom_types
+      .keys()
+      .collect_vec();
+    for classroom_type_key in classroom_type_keys {
+      self.classroom_type_entry(ui, state, classroom_type_key);
+    }
+    if ui.button("+").clicked() {
+      state.add_new_classroom_type();
+    }
+    ui.separator();
+    ui.label(RichText::new("Aulas").strong());
+    let classroom_keys = state
+      .get_simulation_constraints()
+      .classrooms
+      .keys()
+      .collect_vec();
+    ScrollArea::vertical()
+      .auto_shrink([false; 2])
+      .max_height(500.0)
+      .show(ui, |ui| {
+        for classroom_key in classroom_keys {
+          self.classroom_entry(ui, state, classroom_key);
+        }
+      });
+    if ui.button("+").clicked() {
+      let classroom_type_key = state
+        .get_simulation_constraints()
+        .classroom_types
+        .keys()
+        .next();
+      let classroom_type_key = classroom_type_key.unwrap_or_else(|| state.add_new_classroom_type());
+      state.add_new_classroom(classroom_type_key);
+    }
+  }
+}
+
+fn classrooms(state: &SchoolSchedule) -> Vec<(ClassroomKey, Classroom)> {
+  state
+    .get_simulation_constraints()
+    .classrooms
+    .iter()
+    .map(|(classroom_key, classroom)| (classroom_key, classroom.clone()))
+    .collect()
+}
@@ -1,4 +1,4 @@
-use calendars_core::{ClassroomType, Group, ProfessorKey, SchoolSchedule, Semester};
+use calendars_core::{ClassroomTypeKey, Group, ProfessorKey, SchoolSchedule, Semester};
 
 use anyhow::Context;
 use egui::Color32;
@@ -101,11 +101,49 @@ fn get_class_rows(connection: &rusqlite::Connection) -> Result<Vec<ClassRow>, ru
   class_rows.collect()
 }
 
+fn add_classroom_type(schedule: &mut SchoolSchedule, name: &str, lab: bool) -> ClassroomTypeKey {
+  let classroom_type_key = schedule.add_new_classroom_type();
+  schedule
+    .get_classroom_type_metadata_mut(classroom_type_key)
+    .unwrap()
+    .name = name.to_string();
+  schedule
+    .get_classroom_type_mut(classroom_type_key)
+    .unwrap()
+    .lab = lab;
+  classroom_type_key
+}
+
+fn add_classroom(schedule: &mut SchoolSchedule, name: &str, classroom_type_key: ClassroomTypeKey) {
+  let classroom_key = schedule.add_new_classroom(classroom_type_key);
+  schedule
+    .get_classroom_metadata_mut(classroom_key)
+    .unwrap()
+    .name = name.to_string();
+}
+
 fn create_schedule(
   professor_rows: &[ProfessorRow],
   class_rows: &[ClassRow],
 ) -> anyhow::Result<SchoolSchedule> {
   let mut schedule = SchoolSchedule::default();
+  let aula_simple = add_classroom_type(&mut schedule, "Aula Simple", false);
+  let aula_doble = add_classroom_type(&mut schedule, "Aula Doble", false);
+  let lab_quimica = add_classroom_type(&mut schedule, "Lab Quimica", true);
+  let lab_fisica = add_classroom_type(&mut schedule, "Lab Fisica", true);
+  let aula_computo = add_classroom_type(&mut schedule, "Aula Computo", false);
+  for (name, classroom_type_key) in [
+    ("Aula 1", aula_simple),
+    ("Aula 2-3", aula_doble),
+    ("Aula 4", aula_simple),
+    ("Aula 5-6", aula_doble),
+    ("Sala de Seminarios", aula_doble),
+    ("Sala de Computo", aula_computo),
+    ("Lab de Fisica", lab_fisica),
+    ("Lab de Quimica", lab_quimica),
+  ] {
+    add_classroom(&mut schedule, name, classroom_type_key);
+  }
   let mut professors: BTreeMap<&str, ProfessorKey> = BTreeMap::new();
   for professor_row in professor_rows {
     let professor_id = schedule.add_new_professor();
@@ -150,7 +188,7 @@ fn create_schedule(
     theory_class.set_optative(is_optative);
     theory_class.set_professor_id(theory_professor_key);
     theory_class.set_hours(4);
-    theory_class.set_allowed_classroom_types(ClassroomType::AulaSimple | ClassroomType::AulaDoble);
+    theory_class.set_allowed_classroom_types([aula_simple, aula_doble].into());
     let theory_class_metadata = schedule.get_class_metadata_mut(theory_class_key).unwrap();
     theory_class_metadata.rgba = color.to_array();
     theory_class_metadata
@@ -168,13 +206,14 @@ fn create_schedule(
       lab_class.set_optative(is_optative);
       lab_class.set_professor_id(lab_professor_key);
       lab_class.set_hours(3);
-      lab_class.set_allowed_classroom_types(ClassroomType::LabFisica | ClassroomType::LabQuimica);
+      lab_class.set_allowed_classroom_types([lab_fisica, lab_quimica].into());
       let lab_class_metadata = schedule.get_class_metadata_mut(lab_class_key).unwrap();
       lab_class_metadata.rgba = color.to_array();
       lab_class_metadata.name = format!("{} (Lab)", class_row.descripcion);
       lab_class_metadata.class_code.clone_from(&class_row.asign);
     }
   }
+  schedule.update_classroom_assignments();
   Ok(schedule)
 }
 
@@ -0,0 +1,52 @@
+use std::sync::{Arc, Mutex};
+
+use calendars_core::{ClassCalendar, Progress, ProgressObserver};
+
+#[derive(Debug, Default)]
+struct EguiProgressState {
+  fraction_done: f32,
+  /// Hard constraint violations and cost of the best calendar reported so far.
+  best: Option<(u64, f64)>,
+  best_calendar: Option<ClassCalendar>,
+}
+
+/// Keeps the progress of an optimization for the GUI, and repaints it on every report.
+#[derive(Debug, Clone)]
+pub struct EguiProgress {
+  ctx: egui::Context,
+  state: Arc<Mutex<EguiProgressState>>,
+}
+
+impl EguiProgress {
+  pub fn new(ctx: egui::Context) -> Self {
+    Self {
+      ctx,
+      state: Default::default(),
+    }
+  }
+
+  /// From 0 to 1.
+  pub fn fraction_done(&self) -> f32 {
+    self.state.lock().unwrap().fraction_done
+  }
+
+  /// Best calendar reported since the last call, if there's a new one.
+  pub fn take_best_calendar(&self) -> Option<ClassCalendar> {
+    self.state.lock().unwrap().best_calendar.take()
+  }
+}
+
+impl ProgressObserver for EguiProgress {
+  fn on_progress(&self, progress: &Progress) {
+    {
+      let mut state = self.state.lock().unwrap();
+      state.fraction_done = progress.fraction_done.unwrap_or(0.0).clamp(0.0, 1.0) as f32;
+      let best = (progress.best_hard_constraint_violations, progress.best_cost);
+      if state.best.is_none_or(|state_best| best < state_best) {
+        state.best = Some(best);
+        state.best_calendar = Some(progress.best_calendar.clone());
+      }
+    }
+    self.ctx.request_repaint();
+  }
+}
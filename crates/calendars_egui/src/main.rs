@@ -1,11 +1,15 @@
 pub mod app;
+pub mod availability_widget;
 pub mod class_editor;
+pub mod classroom_editor;
 pub mod color_list;
 pub mod database_importer;
+pub mod egui_progress;
 pub mod optimization_widget;
 pub mod professor_editor;
-pub mod professor_schedule_widget;
 pub mod simple_schedule_widget;
+pub mod violations_widget;
+pub mod week_layout_editor;
 
 use crate::app::MyApp;
 
@@ -1,19 +1,151 @@
 use std::time::Duration;
 
-use calendars_core::StopCondition;
+use calendars_core::{
+  ClassCalendar, ExactSolution, HardConstraintViolations, HeuristicCost, HeuristicWeights,
+  InitialTemperature, RunControl, SimulationOutput, StopCondition, TabuSearchOutput,
+  TemperatureFunction,
+};
 use serde::{Deserialize, Serialize};
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OptimizationMethod {
+  #[default]
+  SimulatedAnnealing,
+  TabuSearch,
+  Exact,
+}
+
+impl OptimizationMethod {
+  fn label(&self) -> &'static str {
+    match self {
+      OptimizationMethod::SimulatedAnnealing => "Recocido simulado",
+      OptimizationMethod::TabuSearch => "Busqueda tabu",
+      OptimizationMethod::Exact => "Exacto",
+    }
+  }
+
+  /// Tabu search only stops after a number of iterations, and the exact solver after a number of
+  /// search nodes.
+  fn supports(&self, stop_condition: &StopCondition) -> bool {
+    match self {
+      OptimizationMethod::SimulatedAnnealing => true,
+      OptimizationMethod::TabuSearch | OptimizationMethod::Exact => {
+        matches!(stop_condition, StopCondition::Steps(_))
+      }
+    }
+  }
+}
+
+/// Output of a run of any of the methods. Saved as the output of the method alone.
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum OptimizationResult {
+  SimulatedAnnealing(Box<SimulationOutput>),
+  TabuSearch(Box<TabuSearchOutput>),
+  Exact(Box<ExactSolution>),
+}
+
+impl OptimizationResult {
+  pub fn best_calendar(&self) -> &ClassCalendar {
+    match self {
+      OptimizationResult::SimulatedAnnealing(output) => &output.best_calendar,
+      OptimizationResult::TabuSearch(output) => &output.best_calendar,
+      OptimizationResult::Exact(solution) => &solution.calendar,
+    }
+  }
+
+  pub fn best_cost(&self) -> f64 {
+    match self {
+      OptimizationResult::SimulatedAnnealing(output) => output.best_cost,
+      OptimizationResult::TabuSearch(output) => output.best_cost,
+      OptimizationResult::Exact(solution) => solution.cost_breakdown.iter().map(|c| c.cost).sum(),
+    }
+  }
+
+  pub fn best_hard_constraint_violations(&self) -> HardConstraintViolations {
+    match self {
+      OptimizationResult::SimulatedAnnealing(output) => output.best_hard_constraint_violations,
+      OptimizationResult::TabuSearch(output) => output.best_hard_constraint_violations,
+      // Solutions always satisfy the hard constraints
+      OptimizationResult::Exact(_solution) => HardConstraintViolations::default(),
+    }
+  }
+
+  pub fn final_cost(&self) -> f64 {
+    match self {
+      OptimizationResult::SimulatedAnnealing(output) => output.final_cost,
+      OptimizationResult::TabuSearch(output) => output.final_cost,
+      OptimizationResult::Exact(_solution) => self.best_cost(),
+    }
+  }
+
+  /// `None` for the exact solver, which isn't random.
+  pub fn seed(&self) -> Option<u64> {
+    match self {
+      OptimizationResult::SimulatedAnnealing(output) => Some(output.seed),
+      OptimizationResult::TabuSearch(output) => Some(output.seed),
+      OptimizationResult::Exact(_solution) => None,
+    }
+  }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OptimizationWidget {
   pub open: bool,
+  #[serde(default)]
+  pub method: OptimizationMethod,
   current_stop_condition: StopCondition,
+  #[serde(default)]
+  pub temperature_function: TemperatureFunction,
+  #[serde(default)]
+  pub initial_temperature: InitialTemperature,
+  /// Number of annealing chains run in parallel.
+  #[serde(default = "default_runs")]
+  pub runs: usize,
+  /// Fixed seed to repeat a run. `None` uses a random one.
+  #[serde(default)]
+  pub seed: Option<u64>,
+  /// Start from a greedy schedule instead of the current one.
+  #[serde(default)]
+  pub greedy_start: bool,
+  /// Outputs of the last optimization, best first.
+  #[serde(skip)]
+  pub results: Vec<OptimizationResult>,
+  #[serde(skip)]
+  pub applied_result: Option<usize>,
+  /// Result the user chose to apply, taken by the app.
+  #[serde(skip)]
+  pub result_to_apply: Option<usize>,
+  /// Control of the running optimization.
+  #[serde(skip)]
+  pub control: RunControl,
+  #[serde(skip)]
+  pub hard_constraint_violations: Option<HardConstraintViolations>,
+  #[serde(skip)]
+  pub cost_breakdown: Vec<HeuristicCost>,
+}
+
+fn default_runs() -> usize {
+  1
 }
 
 impl Default for OptimizationWidget {
   fn default() -> Self {
     Self {
       open: true,
+      method: Default::default(),
       current_stop_condition: Default::default(),
+      temperature_function: Default::default(),
+      initial_temperature: Default::default(),
+      runs: default_runs(),
+      seed: None,
+      greedy_start: false,
+      results: Vec::new(),
+      applied_result: None,
+      result_to_apply: None,
+      control: Default::default(),
+      hard_constraint_violations: None,
+      cost_breakdown: Vec::new(),
     }
   }
 }
@@ -22,7 +154,8 @@ impl OptimizationWidget {
   pub fn show(
     &mut self,
     ctx: &egui::Context,
-    pb: Option<&indicatif::ProgressBar>,
+    progress: Option<f32>,
+    heuristic_weights: &mut HeuristicWeights,
   ) -> Option<StopCondition> {
     let mut open = self.open;
     let mut result: Option<StopCondition> = None;
@@ -30,17 +163,248 @@ impl OptimizationWidget {
       .open(&mut open)
       .resizable(true)
       .show(ctx, |ui| {
-        result = self.ui(ui, pb);
+        result = self.ui(ui, progress, heuristic_weights);
       });
     self.open = open;
     result
   }
 
+  fn ui_heuristic_weights(&self, ui: &mut egui::Ui, heuristic_weights: &mut HeuristicWeights) {
+    egui::CollapsingHeader::new("Pesos").show(ui, |ui| {
+      egui::Grid::new("heuristic_weights_grid").show(ui, |ui| {
+        for (label, weight) in [
+          (
+            heuristic_label("classroom_assignment_collisions"),
+            &mut heuristic_weights.classroom_assignment_collisions,
+          ),
+          (
+            heuristic_label("professor_simultaneous_classes"),
+            &mut heuristic_weights.professor_simultaneous_classes,
+          ),
+          (
+            heuristic_label("semester_simultaneous_classes"),
+            &mut heuristic_weights.semester_simultaneous_classes,
+          ),
+          (
+            heuristic_label("labs_on_different_days"),
+            &mut heuristic_weights.labs_on_different_days,
+          ),
+          (
+            heuristic_label("professor_not_available"),
+            &mut heuristic_weights.professor_not_available,
+          ),
+          (
+            heuristic_label("incontinuous_classes"),
+            &mut heuristic_weights.incontinuous_classes,
+          ),
+          (
+            heuristic_label("outside_session_length"),
+            &mut heuristic_weights.outside_session_length,
+          ),
+          (
+            heuristic_label("holes_per_semester"),
+            &mut heuristic_weights.holes_per_semester,
+          ),
+          (
+            heuristic_label("professor_available_if_needed"),
+            &mut heuristic_weights.professor_available_if_needed,
+          ),
+          (
+            heuristic_label("inconsistent_class_timeslots"),
+            &mut heuristic_weights.inconsistent_class_timeslots,
+          ),
+          (
+            heuristic_label("classroom_changes"),
+            &mut heuristic_weights.classroom_changes,
+          ),
+          (
+            heuristic_label("simultaneous_classes"),
+            &mut heuristic_weights.simultaneous_classes,
+          ),
+        ] {
+          ui.label(label);
+          ui.add(egui::DragValue::new(weight).speed(10));
+          ui.end_row();
+        }
+      });
+      if ui.button("Restablecer").clicked() {
+        *heuristic_weights = Default::default();
+      }
+    });
+  }
+
+  fn ui_temperature(&mut self, ui: &mut egui::Ui) {
+    egui::CollapsingHeader::new("Temperatura").show(ui, |ui| {
+      egui::ComboBox::from_label("Funcion de temperatura")
+        .selected_text(temperature_function_label(&self.temperature_function))
+        .show_ui(ui, |ui| {
+          for temperature_function in [
+            TemperatureFunction::Linear,
+            TemperatureFunction::Exponential,
+            TemperatureFunction::Logarithmic,
+            TemperatureFunction::Reheating { cycles: 3 },
+          ] {
+            let label = temperature_function_label(&temperature_function);
+            ui.selectable_value(&mut self.temperature_function, temperature_function, label);
+          }
+        });
+      if let TemperatureFunction::Reheating { cycles } = &mut self.temperature_function {
+        ui.horizontal(|ui| {
+          ui.add(egui::DragValue::new(cycles).clamp_range(1..=100));
+          ui.label("ciclos");
+        });
+      }
+      let mut auto = matches!(self.initial_temperature, InitialTemperature::Auto { .. });
+      if ui
+        .checkbox(&mut auto, "Temperatura inicial automatica")
+        .clicked()
+      {
+        self.initial_temperature = if auto {
+          InitialTemperature::Auto {
+            acceptance_probability: 0.8,
+          }
+        } else {
+          InitialTemperature::default()
+        };
+      }
+      ui.horizontal(|ui| match &mut self.initial_temperature {
+        InitialTemperature::Fixed(temperature) => {
+          ui.add(
+            egui::DragValue::new(temperature)
+              .speed(0.1)
+              .clamp_range(0.0..=f64::MAX),
+          );
+          ui.label("Temperatura inicial");
+        }
+        InitialTemperature::Auto {
+          acceptance_probability,
+        } => {
+          ui.add(
+            egui::DragValue::new(acceptance_probability)
+              .speed(0.01)
+              .clamp_range(0.01..=0.99),
+          );
+          ui.label("Probabilidad inicial de aceptar un empeoramiento");
+        }
+      });
+    });
+  }
+
+  fn ui_results(&mut self, ui: &mut egui::Ui) {
+    if self.results.is_empty() {
+      return;
+    }
+    egui::CollapsingHeader::new("Resultados")
+      .default_open(true)
+      .show(ui, |ui| {
+        egui::Grid::new("results_grid")
+          .striped(true)
+          .show(ui, |ui| {
+            ui.strong("#");
+            ui.strong("Violaciones duras");
+            ui.strong("Mejor costo");
+            ui.strong("Costo final");
+            ui.strong("Semilla");
+            ui.end_row();
+            for (i, result) in self.results.iter().enumerate() {
+              ui.label((i + 1).to_string());
+              ui.label(result.best_hard_constraint_violations().total().to_string());
+              ui.label(format!("{:.3}", result.best_cost()));
+              ui.label(format!("{:.3}", result.final_cost()));
+              ui.label(
+                result
+                  .seed()
+                  .map_or_else(|| "-".to_string(), |seed| seed.to_string()),
+              );
+              if self.applied_result == Some(i) {
+                ui.label("Aplicado");
+              } else if ui.button("Aplicar").clicked() {
+                self.result_to_apply = Some(i);
+              }
+              ui.end_row();
+            }
+          });
+      });
+  }
+
+  fn ui_hard_constraint_violations(&self, ui: &mut egui::Ui) {
+    let Some(violations) = self.hard_constraint_violations else {
+      return;
+    };
+    ui.separator();
+    if violations.is_feasible() {
+      ui.label("Se encontro un horario factible.");
+      return;
+    }
+    ui.colored_label(
+      ui.visuals().error_fg_color,
+      "No se encontro un horario factible.",
+    );
+    egui::Grid::new("hard_constraint_violations_grid").show(ui, |ui| {
+      ui.label("Profesor no disponible");
+      ui.label(violations.professor_not_available.to_string());
+      ui.end_row();
+      ui.label("Profesor con clases simultaneas");
+      ui.label(violations.professor_double_booking.to_string());
+      ui.end_row();
+      ui.label("Clases sin aula");
+      ui.label(violations.classroom_overflow.to_string());
+      ui.end_row();
+    });
+  }
+
+  fn ui_cost_breakdown(&self, ui: &mut egui::Ui) {
+    if self.cost_breakdown.is_empty() {
+      return;
+    }
+    egui::CollapsingHeader::new("Desglose del costo").show(ui, |ui| {
+      egui::Grid::new("cost_breakdown_grid")
+        .striped(true)
+        .show(ui, |ui| {
+          ui.strong("Heuristica");
+          ui.strong("Violaciones");
+          ui.strong("Peso");
+          ui.strong("Costo");
+          ui.end_row();
+          for heuristic_cost in self.cost_breakdown.iter() {
+            ui.label(heuristic_label(&heuristic_cost.name));
+            ui.label(heuristic_cost.violations.to_string());
+            ui.label(heuristic_cost.weight.to_string());
+            ui.label(format!("{:.3}", heuristic_cost.cost));
+            ui.end_row();
+          }
+          ui.strong("Total");
+          ui.label("");
+          ui.label("");
+          ui.strong(format!(
+            "{:.3}",
+            self.cost_breakdown.iter().map(|c| c.cost).sum::<f64>()
+          ));
+          ui.end_row();
+        });
+    });
+  }
+
   fn ui(
     &mut self,
     ui: &mut egui::Ui,
-    pb: Option<&indicatif::ProgressBar>,
+    progress: Option<f32>,
+    heuristic_weights: &mut HeuristicWeights,
   ) -> Option<StopCondition> {
+    egui::ComboBox::from_label("Metodo")
+      .selected_text(self.method.label())
+      .show_ui(ui, |ui| {
+        for method in [
+          OptimizationMethod::SimulatedAnnealing,
+          OptimizationMethod::TabuSearch,
+          OptimizationMethod::Exact,
+        ] {
+          ui.selectable_value(&mut self.method, method, method.label());
+        }
+      });
+    if !self.method.supports(&self.current_stop_condition) {
+      self.current_stop_condition = StopCondition::Steps(0);
+    }
     if ui
       .add(egui::RadioButton::new(
         matches!(self.current_stop_condition, StopCondition::Steps(_)),
@@ -50,12 +414,13 @@ impl OptimizationWidget {
     {
       self.current_stop_condition = StopCondition::Steps(0);
     };
-    if ui
-      .add(egui::RadioButton::new(
-        matches!(self.current_stop_condition, StopCondition::Time(_)),
-        "Tiempo de simulacion",
-      ))
-      .clicked()
+    if self.method == OptimizationMethod::SimulatedAnnealing
+      && ui
+        .add(egui::RadioButton::new(
+          matches!(self.current_stop_condition, StopCondition::Time(_)),
+          "Tiempo de simulacion",
+        ))
+        .clicked()
     {
       self.current_stop_condition = StopCondition::Time(Duration::ZERO);
     }
@@ -69,18 +434,103 @@ impl OptimizationWidget {
         ui.label("segundos");
         *d = Duration::from_secs(n);
       }
+      // Not offered by the widget
+      _ => (),
     };
-    if let Some(pb) = pb {
-      let l = pb.length().unwrap();
-      let i = pb.position();
-      let p = (i as f32) / (l as f32);
-      let pb = egui::ProgressBar::new(p).show_percentage();
+    if self.method == OptimizationMethod::SimulatedAnnealing {
+      ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut self.runs).clamp_range(1..=16));
+        ui.label("Cadenas en paralelo");
+      });
+    }
+    if self.method != OptimizationMethod::Exact {
+      ui.horizontal(|ui| {
+        let mut fixed_seed = self.seed.is_some();
+        if ui.checkbox(&mut fixed_seed, "Semilla fija").clicked() {
+          // Start from the seed of the applied result, to repeat it
+          self.seed = fixed_seed.then(|| {
+            self
+              .applied_result
+              .and_then(|i| self.results.get(i))
+              .and_then(|result| result.seed())
+              .unwrap_or_default()
+          });
+        }
+        if let Some(seed) = self.seed.as_mut() {
+          ui.add(egui::DragValue::new(seed));
+        }
+      });
+    }
+    ui.checkbox(&mut self.greedy_start, "Partir de un horario voraz")
+      .on_hover_text("Acomoda las clases no fijadas evitando conflictos antes de optimizar.");
+    if self.method == OptimizationMethod::SimulatedAnnealing {
+      self.ui_temperature(ui);
+    }
+    self.ui_heuristic_weights(ui, heuristic_weights);
+    self.ui_results(ui);
+    self.ui_hard_constraint_violations(ui);
+    self.ui_cost_breakdown(ui);
+    if let Some(progress) = progress {
+      let pb = egui::ProgressBar::new(progress).show_percentage();
       ui.add(pb);
+      ui.horizontal(|ui| {
+        if self.control.is_paused() {
+          if ui.button("Reanudar").clicked() {
+            self.control.resume();
+          }
+        } else if ui
+          .add_enabled(!self.control.is_stopped(), egui::Button::new("Pausar"))
+          .clicked()
+        {
+          self.control.pause();
+        }
+        if ui
+          .add_enabled(!self.control.is_stopped(), egui::Button::new("Detener"))
+          .on_hover_text("Termina la optimizacion y aplica el mejor resultado hasta ahora.")
+          .clicked()
+        {
+          self.control.stop();
+        }
+      });
       None
-    } else if ui.button("Optimizar").clicked() {
+    } else if ui
+      .add_enabled(
+        self.method.supports(&self.current_stop_condition),
+        egui::Button::new("Optimizar"),
+      )
+      .clicked()
+    {
       Some(self.current_stop_condition.clone())
     } else {
       None
     }
   }
 }
+
+fn temperature_function_label(temperature_function: &TemperatureFunction) -> &'static str {
+  match temperature_function {
+    TemperatureFunction::Linear => "Lineal",
+    TemperatureFunction::Exponential => "Exponencial",
+    TemperatureFunction::Logarithmic => "Logaritmica",
+    TemperatureFunction::Reheating { .. } => "Con recalentamiento",
+  }
+}
+
+/// Name shown in the GUI for a heuristic. Unknown (custom) heuristics keep their own name.
+pub fn heuristic_label(name: &str) -> &str {
+  match name {
+    "classroom_assignment_collisions" => "Clases sin aula",
+    "professor_simultaneous_classes" => "Profesor con clases simultaneas",
+    "semester_simultaneous_classes" => "Semestre con clases simultaneas",
+    "labs_on_different_days" => "Laboratorio en varios dias",
+    "professor_not_available" => "Profesor no disponible",
+    "incontinuous_classes" => "Clases discontinuas",
+    "outside_session_length" => "Sesiones muy cortas o largas",
+    "holes_per_semester" => "Horas libres por semestre",
+    "professor_available_if_needed" => "Profesor disponible si es necesario",
+    "inconsistent_class_timeslots" => "Clases en horas distintas",
+    "classroom_changes" => "Cambios de aula",
+    "simultaneous_classes" => "Clases simultaneas",
+    _ => name,
+  }
+}
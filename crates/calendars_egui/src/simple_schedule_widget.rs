@@ -1,18 +1,42 @@
 use calendars_core::{
-  ClassFilter, ClassKey, Classroom, Day, ProfessorKey, SchoolSchedule, Semester, Timeslot,
-  WeekCalendar,
+  ClassFilter, ClassroomAssignmentKey, ClassroomKey, Day, ProfessorKey, SchoolSchedule, Semester,
+  Timeslot, WeekCalendar, WeekLayout,
 };
 use egui::{Align2, Color32, FontId, Rect, Rounding, Sense, Stroke};
 use serde::{Deserialize, Serialize};
 
+pub const DAY_NAMES: [&str; 7] = [
+  "Lunes",
+  "Martes",
+  "Miercoles",
+  "Jueves",
+  "Viernes",
+  "Sabado",
+  "Domingo",
+];
+
+const TIME_LABEL_WIDTH: f32 = 100.0;
+const DAY_LABEL_HEIGHT: f32 = 20.0;
+
+pub fn timeslot_label(week_layout: &WeekLayout, timeslot: Timeslot) -> String {
+  format!(
+    "{} - {}",
+    week_layout.timeslot_start(timeslot).format("%H:%M"),
+    week_layout.timeslot_end(timeslot).format("%H:%M")
+  )
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct SimpleScheduleWidget {
   pub class_filter: ClassFilter,
   pub open: bool,
+  /// Cells outlined on top of the schedule, e.g. the ones of a selected violation.
+  #[serde(skip)]
+  pub highlighted_cells: Vec<(Day, Timeslot)>,
 }
 
 impl SimpleScheduleWidget {
-  pub fn show(&mut self, ctx: &egui::Context, state: &SchoolSchedule) {
+  pub fn show(&mut self, ctx: &egui::Context, state: &mut SchoolSchedule) {
     let mut open = self.open;
     egui::Window::new("Horario")
       .open(&mut open)
@@ -24,31 +48,57 @@ impl SimpleScheduleWidget {
       });
     self.open = open;
   }
-  fn ui_calendar(&self, ui: &mut egui::Ui, state: &SchoolSchedule) {
-    let (response, painter) = ui.allocate_painter(ui.available_size_before_wrap(), Sense::hover());
-    let total_width = response.rect.width();
-    let total_height = response.rect.height();
-    let w = total_width / Day::all().len() as f32;
-    let h: f32 = total_height / Timeslot::all().len() as f32;
-    let mut classes_to_draw: WeekCalendar<Vec<ClassKey>> = Default::default();
-    for class_entry in state
-      .class_calendar()
-      .class_entries()
-      .iter()
-      .filter(|class_entry| state.filter_class(class_entry, &self.class_filter))
+  fn ui_calendar(&self, ui: &mut egui::Ui, state: &mut SchoolSchedule) {
+    let (response, painter) = ui.allocate_painter(ui.available_size_before_wrap(), Sense::click());
+    let response = response.on_hover_text("Haz clic en una clase para fijarla o liberarla.");
+    let clicked_pos = response
+      .clicked()
+      .then(|| response.interact_pointer_pos())
+      .flatten();
+    let mut clicked_class = None;
+    let calendar_rect = response
+      .rect
+      .with_min_x(response.rect.min.x + TIME_LABEL_WIDTH);
+    let calendar_rect = calendar_rect.with_min_y(calendar_rect.min.y + DAY_LABEL_HEIGHT);
+    let total_width = calendar_rect.width();
+    let total_height = calendar_rect.height();
+    let week_layout = state.week_layout();
+    let w = total_width / week_layout.day_count() as f32;
+    let h: f32 = total_height / week_layout.timeslot_count() as f32;
+    for day in week_layout.days() {
+      painter.text(
+        calendar_rect.left_top() + (w * (usize::from(day) as f32 + 0.5), -DAY_LABEL_HEIGHT).into(),
+        Align2::CENTER_TOP,
+        DAY_NAMES[usize::from(day)],
+        FontId::default(),
+        ui.visuals().text_color(),
+      );
+    }
+    for timeslot in week_layout.timeslots() {
+      painter.text(
+        calendar_rect.left_top() + (-TIME_LABEL_WIDTH, h * usize::from(timeslot) as f32).into(),
+        Align2::LEFT_TOP,
+        timeslot_label(week_layout, timeslot),
+        FontId::default(),
+        ui.visuals().text_color(),
+      );
+    }
+    let mut classes_to_draw: WeekCalendar<Vec<ClassroomAssignmentKey>> =
+      WeekCalendar::new(week_layout);
+    for key in state
+      .classroom_assignment_keys()
+      .filter(|key| state.filter_class(key, &self.class_filter))
     {
-      classes_to_draw
-        .get_mut(class_entry.day, class_entry.timeslot)
-        .push(class_entry.class_key);
+      classes_to_draw.get_mut(key.day, key.timeslot).push(key);
     }
-    for day in Day::all() {
-      for timeslot in Timeslot::all() {
+    for day in week_layout.days() {
+      for timeslot in week_layout.timeslots() {
         let classes_to_draw = classes_to_draw.get(day, timeslot);
         let total_count = classes_to_draw.len();
 
         let class_width = w / total_count as f32;
 
-        let mut topleft: egui::Pos2 = response.rect.left_top()
+        let mut topleft: egui::Pos2 = calendar_rect.left_top()
           + (
             w * usize::from(day) as f32,
             h * usize::from(timeslot) as f32,
@@ -61,16 +111,26 @@ impl SimpleScheduleWidget {
           Stroke::new(1.0, Color32::from_gray(100)),
         );
 
-        for class_key in classes_to_draw {
+        for key in classes_to_draw {
+          let class_key = &key.class_key;
           let class_metadata = state.get_class_metadata(*class_key).unwrap();
           let botright: egui::Pos2 = topleft + (class_width, h).into();
           let rgba = class_metadata.rgba;
           let class_color = Color32::from_rgba_premultiplied(rgba[0], rgba[1], rgba[2], rgba[3]);
+          let class_rect = Rect::from_two_pos(topleft, botright);
+          if clicked_pos.is_some_and(|pos| class_rect.contains(pos)) {
+            clicked_class = Some((day, timeslot, *class_key));
+          }
+          let stroke = if state.class_calendar().is_locked(day, timeslot, *class_key) {
+            Stroke::new(3.0, Color32::BLACK)
+          } else {
+            Stroke::new(1.0, Color32::from_gray(100))
+          };
           painter.rect(
-            Rect::from_two_pos(topleft, botright),
+            class_rect,
             Rounding::same(0.02 * w.min(h)),
             class_color,
-            Stroke::new(1.0, Color32::from_gray(100)),
+            stroke,
           );
           let class_code = &class_metadata.class_code;
           painter.text(
@@ -80,10 +140,41 @@ impl SimpleScheduleWidget {
             FontId::default(),
             Color32::BLACK,
           );
+          let classroom_name = state
+            .classroom_assignments()
+            .get(key)
+            .and_then(|classroom_key| state.get_classroom_metadata(*classroom_key))
+            .map(|classroom_metadata| classroom_metadata.name.as_str());
+          if let Some(classroom_name) = classroom_name {
+            painter.text(
+              topleft + (0.0, h).into(),
+              Align2::LEFT_BOTTOM,
+              classroom_name,
+              FontId::proportional(10.0),
+              Color32::BLACK,
+            );
+          }
           topleft += (class_width, 0.0).into();
         }
       }
     }
+    for (day, timeslot) in self.highlighted_cells.iter() {
+      let topleft: egui::Pos2 = calendar_rect.left_top()
+        + (
+          w * usize::from(*day) as f32,
+          h * usize::from(*timeslot) as f32,
+        )
+          .into();
+      painter.rect_stroke(
+        Rect::from_two_pos(topleft, topleft + (w, h).into()),
+        Rounding::ZERO,
+        Stroke::new(3.0, ui.visuals().error_fg_color),
+      );
+    }
+    if let Some((day, timeslot, class_key)) = clicked_class {
+      let locked = state.class_calendar().is_locked(day, timeslot, class_key);
+      state.set_locked(day, timeslot, class_key, !locked);
+    }
   }
   fn ui_control_export(&mut self, ui: &mut egui::Ui, state: &SchoolSchedule) {
     if ui.button("Exportar").clicked() {
@@ -172,57 +263,41 @@ impl SimpleScheduleWidget {
     });
 
     ui.horizontal(|ui| {
-      if ui
-        .radio(
-          matches!(self.class_filter, ClassFilter::Classroom(_)),
-          "Aula",
-        )
-        .clicked()
-        && !matches!(self.class_filter, ClassFilter::Classroom(_))
-      {
-        self.class_filter = ClassFilter::Classroom(Classroom::Aula5_6);
-      }
-      if let ClassFilter::Classroom(classroom) = &mut self.class_filter {
-        egui::ComboBox::new("schedule_widget_combo_box_2", "")
-          .selected_text(classroom.to_string())
+      let classroom_key = state.get_simulation_constraints().classrooms.keys().next();
+      ui.add_enabled_ui(classroom_key.is_some(), |ui| {
+        if ui
+          .radio(
+            matches!(self.class_filter, ClassFilter::Classroom(_)),
+            "Aula",
+          )
+          .clicked()
+          && !matches!(self.class_filter, ClassFilter::Classroom(_))
+        {
+          self.class_filter =
+            ClassFilter::Classroom(classroom_key.expect("Radio shouldn't be enabled"));
+        }
+      });
+      if let ClassFilter::Classroom(classroom_key) = &mut self.class_filter {
+        egui::ComboBox::new("schedule_widget_combo_box_3", "")
+          .selected_text(
+            state
+              .get_classroom_metadata(*classroom_key)
+              .map(|classroom| classroom.name.clone())
+              .unwrap_or("Aula Inexistente".to_string()),
+          )
           .show_ui(ui, |ui| {
-            ui.selectable_value(classroom, Classroom::Aula1, Classroom::Aula1.to_string());
-            ui.selectable_value(
-              classroom,
-              Classroom::Aula2_3,
-              Classroom::Aula2_3.to_string(),
-            );
-            ui.selectable_value(classroom, Classroom::Aula4, Classroom::Aula4.to_string());
-            ui.selectable_value(
-              classroom,
-              Classroom::Aula5_6,
-              Classroom::Aula5_6.to_string(),
-            );
-            ui.selectable_value(
-              classroom,
-              Classroom::SalaSeminarios,
-              Classroom::SalaSeminarios.to_string(),
-            );
-            ui.selectable_value(
-              classroom,
-              Classroom::SalaComputo,
-              Classroom::SalaComputo.to_string(),
-            );
-            ui.selectable_value(
-              classroom,
-              Classroom::LabFisica,
-              Classroom::LabFisica.to_string(),
-            );
-            ui.selectable_value(
-              classroom,
-              Classroom::LabQuimica,
-              Classroom::LabQuimica.to_string(),
-            );
-            ui.selectable_value(
-              classroom,
-              Classroom::NotAssigned,
-              "No Asignados (choque de clases)",
-            )
+            let classroom_keys: Vec<ClassroomKey> = state
+              .get_simulation_constraints()
+              .classrooms
+              .keys()
+              .collect();
+            for i in classroom_keys {
+              ui.selectable_value(
+                classroom_key,
+                i,
+                state.get_classroom_metadata(i).unwrap().name.clone(),
+              );
+            }
           });
       }
     });
@@ -238,7 +313,7 @@ impl SimpleScheduleWidget {
     });
   }
 
-  fn ui(&mut self, ui: &mut egui::Ui, state: &SchoolSchedule) {
+  fn ui(&mut self, ui: &mut egui::Ui, state: &mut SchoolSchedule) {
     self.ui_control(ui, state);
     ui.separator();
     self.ui_calendar(ui, state);
@@ -0,0 +1,115 @@
+use calendars_core::{Day, SchoolSchedule, Timeslot, Violation};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  optimization_widget::heuristic_label,
+  simple_schedule_widget::{timeslot_label, DAY_NAMES},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ViolationsWidget {
+  pub open: bool,
+  #[serde(skip)]
+  violations: Vec<Violation>,
+  #[serde(skip)]
+  selected_violation: Option<usize>,
+}
+
+impl ViolationsWidget {
+  pub fn show(&mut self, ctx: &egui::Context, state: &SchoolSchedule) {
+    let mut open = self.open;
+    egui::Window::new("Violaciones")
+      .open(&mut open)
+      .resizable(true)
+      .vscroll(true)
+      .show(ctx, |ui| {
+        self.ui(ui, state);
+      });
+    self.open = open;
+  }
+
+  /// Recomputes the violations of the schedule. Needed after the schedule changes.
+  pub fn refresh(&mut self, state: &SchoolSchedule) {
+    self.violations = state.violations();
+    self.selected_violation = None;
+  }
+
+  /// Cells of the selected violation, to be highlighted in the schedule.
+  pub fn highlighted_cells(&self) -> Vec<(Day, Timeslot)> {
+    self
+      .selected_violation
+      .and_then(|i| self.violations.get(i))
+      .map(|violation| violation.cells.clone())
+      .unwrap_or_default()
+  }
+
+  fn violation_label(violation: &Violation, state: &SchoolSchedule) -> String {
+    let classes = violation
+      .class_keys
+      .iter()
+      .map(|class_key| {
+        let class_name = state
+          .get_class_metadata(*class_key)
+          .map(|class| class.name.as_str())
+          .unwrap_or("Clase Inexistente");
+        let professor_name = state
+          .get_class(*class_key)
+          .and_then(|class| state.get_professor_metadata(class.professor_key))
+          .map(|professor| professor.name.as_str())
+          .unwrap_or("Profesor Inexistente");
+        format!("{} ({})", class_name, professor_name)
+      })
+      .join(", ");
+    let cells = violation
+      .cells
+      .iter()
+      .map(|(day, timeslot)| {
+        format!(
+          "{} {}",
+          DAY_NAMES[usize::from(*day)],
+          timeslot_label(state.week_layout(), *timeslot)
+        )
+      })
+      .join(", ");
+    format!("{}: {}", classes, cells)
+  }
+
+  fn ui(&mut self, ui: &mut egui::Ui, state: &SchoolSchedule) {
+    if ui.button("Actualizar").clicked() {
+      self.refresh(state);
+    }
+    if self.violations.is_empty() {
+      ui.label("No hay violaciones.");
+      return;
+    }
+    ui.label("Haz clic en una violacion para resaltarla en el horario.");
+    let heuristics = self
+      .violations
+      .iter()
+      .map(|violation| violation.heuristic.as_str())
+      .unique()
+      .collect_vec();
+    for heuristic in heuristics {
+      let indices = self
+        .violations
+        .iter()
+        .positions(|violation| violation.heuristic == heuristic)
+        .collect_vec();
+      egui::CollapsingHeader::new(format!(
+        "{} ({})",
+        heuristic_label(heuristic),
+        indices.len()
+      ))
+      .show(ui, |ui| {
+        for i in indices {
+          let selected = self.selected_violation == Some(i);
+          let label = Self::violation_label(&self.violations[i], state);
+          if ui.selectable_label(selected, label).clicked() {
+            self.selected_violation = if selected { None } else { Some(i) };
+          }
+        }
+      });
+    }
+  }
+}
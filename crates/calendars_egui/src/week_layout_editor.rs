@@ -0,0 +1,146 @@
+use calendars_core::{SchoolSchedule, TimeslotTimes, WeekLayout};
+use chrono::{Duration, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeekLayoutEditor {
+  pub open: bool,
+  day_count: usize,
+  timeslots: Vec<TimeslotTimes>,
+  periodic_first_start: NaiveTime,
+  periodic_timeslot_minutes: i64,
+  periodic_passing_minutes: i64,
+  periodic_timeslot_count: usize,
+}
+
+impl Default for WeekLayoutEditor {
+  fn default() -> Self {
+    let week_layout = WeekLayout::default();
+    Self {
+      open: false,
+      day_count: week_layout.day_count(),
+      timeslots: week_layout.timeslot_times().to_vec(),
+      periodic_first_start: week_layout.timeslot_times()[0].start,
+      periodic_timeslot_minutes: 50,
+      periodic_passing_minutes: 10,
+      periodic_timeslot_count: week_layout.timeslot_count(),
+    }
+  }
+}
+
+fn time_edit(ui: &mut egui::Ui, time: &mut NaiveTime) {
+  let mut hour = time.hour();
+  let mut minute = time.minute();
+  ui.add(egui::DragValue::new(&mut hour).clamp_range(0..=23));
+  ui.label(":");
+  ui.add(egui::DragValue::new(&mut minute).clamp_range(0..=59));
+  *time = NaiveTime::from_hms_opt(hour, minute, time.second()).unwrap();
+}
+
+impl WeekLayoutEditor {
+  pub fn show(&mut self, ctx: &egui::Context, state: &mut SchoolSchedule, enabled: bool) {
+    let mut open = self.open;
+    egui::Window::new("Semana")
+      .open(&mut open)
+      .enabled(enabled)
+      .resizable(true)
+      .show(ctx, |ui| {
+        self.ui(ui, state);
+      });
+    self.open = open;
+  }
+
+  fn ui_periodic(&mut self, ui: &mut egui::Ui) {
+    ui.label(egui::RichText::new("Generar horarios").strong());
+    ui.horizontal(|ui| {
+      ui.label("Inicio");
+      time_edit(ui, &mut self.periodic_first_start);
+    });
+    ui.horizontal(|ui| {
+      ui.label("Duracion (min.)");
+      ui.add(egui::DragValue::new(&mut self.periodic_timeslot_minutes).clamp_range(5..=240));
+      ui.label("Descanso (min.)");
+      ui.add(egui::DragValue::new(&mut self.periodic_passing_minutes).clamp_range(0..=120));
+    });
+    ui.horizontal(|ui| {
+      ui.label("Cantidad");
+      ui.add(egui::DragValue::new(&mut self.periodic_timeslot_count).clamp_range(1..=96));
+    });
+    let week_layout = WeekLayout::periodic(
+      self.day_count,
+      self.periodic_first_start,
+      Duration::minutes(self.periodic_timeslot_minutes),
+      Duration::minutes(self.periodic_passing_minutes),
+      self.periodic_timeslot_count,
+    );
+    ui.add_enabled_ui(week_layout.is_ok(), |ui| {
+      if ui.button("Generar").clicked() {
+        self.timeslots = week_layout.unwrap().timeslot_times().to_vec();
+      }
+    });
+  }
+
+  fn ui_timeslots(&mut self, ui: &mut egui::Ui) {
+    let mut removed_timeslot = None;
+    egui::ScrollArea::vertical()
+      .max_height(300.0)
+      .show(ui, |ui| {
+        for (i, timeslot) in self.timeslots.iter_mut().enumerate() {
+          ui.horizontal(|ui| {
+            time_edit(ui, &mut timeslot.start);
+            ui.label("-");
+            time_edit(ui, &mut timeslot.end);
+            if ui.button("x").clicked() {
+              removed_timeslot = Some(i);
+            }
+          });
+        }
+      });
+    if let Some(i) = removed_timeslot {
+      self.timeslots.remove(i);
+    }
+    if ui.button("+").clicked() {
+      let new_timeslot = self
+        .timeslots
+        .last()
+        .map(|last| TimeslotTimes {
+          start: last.end,
+          end: last.end + (last.end - last.start),
+        })
+        .unwrap_or(WeekLayout::default().timeslot_times()[0]);
+      self.timeslots.push(new_timeslot);
+    }
+  }
+
+  fn ui(&mut self, ui: &mut egui::Ui, state: &mut SchoolSchedule) {
+    ui.horizontal(|ui| {
+      ui.label("Dias");
+      ui.add(egui::DragValue::new(&mut self.day_count).clamp_range(1..=7));
+    });
+    ui.separator();
+    self.ui_timeslots(ui);
+    ui.separator();
+    self.ui_periodic(ui);
+    ui.separator();
+    let week_layout = WeekLayout::new(self.day_count, self.timeslots.clone());
+    if let Err(e) = &week_layout {
+      ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+    }
+    ui.horizontal(|ui| {
+      ui.add_enabled_ui(week_layout.is_ok(), |ui| {
+        if ui
+          .button("Aplicar")
+          .on_hover_text("Las clases fuera de la nueva semana se moveran al horario mas cercano.")
+          .clicked()
+        {
+          state.set_week_layout(week_layout.unwrap());
+        }
+      });
+      if ui.button("Restablecer").clicked() {
+        let week_layout = state.week_layout();
+        self.day_count = week_layout.day_count();
+        self.timeslots = week_layout.timeslot_times().to_vec();
+      }
+    });
+  }
+}